@@ -0,0 +1,97 @@
+//! Measures how long extension runtimes take to start, cold (no snapshot, no
+//! code cache, nothing transpiled yet) and warm (everything cached).
+//!
+//! Run with `cargo bench -p core --features bench --bench extension_startup
+//! [extension dir...]`.
+//! Without arguments every extension in the user's extensions directory is
+//! measured.
+
+use std::{path::PathBuf, time::Duration};
+
+use core::extensions::ExtensionManager;
+
+const RUNS: u32 = 5;
+
+fn main() -> anyhow::Result<()> {
+    let extensions = extension_dirs()?;
+    if extensions.is_empty() {
+        println!("No extensions to measure");
+        return Ok(());
+    }
+
+    let cache_dir = std::env::temp_dir().join(format!("cognito-bench-{}", std::process::id()));
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    println!("{:<32} {:>12} {:>12}", "extension", "cold", "warm");
+    for path in extensions {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let result = runtime.block_on(async {
+            // Fills the caches, and makes sure V8 itself is initialized
+            // before anything is timed
+            ExtensionManager::measure_startup(path.clone(), Some(cache_dir.clone())).await?;
+
+            let cold = average(|| ExtensionManager::measure_startup(path.clone(), None)).await?;
+            let warm = average(|| {
+                ExtensionManager::measure_startup(path.clone(), Some(cache_dir.clone()))
+            })
+            .await?;
+
+            anyhow::Ok((cold, warm))
+        });
+
+        match result {
+            Ok((cold, warm)) => println!("{name:<32} {cold:>12.2?} {warm:>12.2?}"),
+            Err(e) => println!("{name:<32} failed: {e}"),
+        }
+    }
+
+    let _ = std::fs::remove_dir_all(cache_dir);
+
+    Ok(())
+}
+
+async fn average<F, Fut>(mut measure: F) -> anyhow::Result<Duration>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<Duration>>,
+{
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        total += measure().await?;
+    }
+
+    Ok(total / RUNS)
+}
+
+fn extension_dirs() -> anyhow::Result<Vec<PathBuf>> {
+    let args: Vec<PathBuf> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .map(PathBuf::from)
+        .collect();
+    if !args.is_empty() {
+        return Ok(args);
+    }
+
+    let Some(dir) = dirs::config_dir().map(|dir| dir.join("cognito").join("extensions")) else {
+        return Ok(Vec::new());
+    };
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut extensions = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            extensions.push(path);
+        }
+    }
+    extensions.sort();
+
+    Ok(extensions)
+}
@@ -1,25 +1,231 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
 
-use crate::{AppContext, state::Item};
+use crate::{AppContext, forms::Form, state::Item};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Action {
     pub title: String,
+    #[serde(default)]
     pub description: Option<String>,
+    #[serde(default)]
     pub icon: Option<String>,
     pub command_type: CommandType,
+    /// Keys that run the action while its item is selected, e.g.
+    /// `"ctrl+shift+c"`. At least one of `ctrl`, `alt` or `super` is needed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shortcut: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+impl Action {
+    /// Whether the action's title or description contains `filter`,
+    /// ignoring case.
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        self.title.to_lowercase().contains(&filter)
+            || self
+                .description
+                .as_ref()
+                .is_some_and(|description| description.to_lowercase().contains(&filter))
+    }
+
+    /// Whether `pressed`, as returned by [`normalize_shortcut`], is the
+    /// action's shortcut.
+    pub fn has_shortcut(&self, pressed: &str) -> bool {
+        self.shortcut
+            .as_deref()
+            .is_some_and(|shortcut| normalize_shortcut(shortcut) == pressed)
+    }
+}
+
+/// A value an item's actions take, typed in the search bar after the item,
+/// e.g. the minutes of "Start timer".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Argument {
+    pub name: String,
+    /// Shown while the argument is empty, the name when not given.
+    #[serde(default)]
+    pub placeholder: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl Argument {
+    pub fn placeholder(&self) -> &str {
+        self.placeholder.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// `text` split at whitespace into values for `count` arguments. The last
+/// argument takes the rest, so "translate <text>" takes whole sentences.
+pub fn split_arguments(text: &str, count: usize) -> Vec<String> {
+    let mut values = Vec::with_capacity(count);
+    let mut rest = text.trim();
+    while values.len() + 1 < count && !rest.is_empty() {
+        let (value, remainder) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        values.push(value.to_string());
+        rest = remainder.trim_start();
+    }
+    if count > 0 && !rest.is_empty() {
+        values.push(rest.to_string());
+    }
+    values.resize(count, String::new());
+    values
+}
+
+/// `payload` with `key` set to `value`. A payload that isn't an object is
+/// kept as `payload` next to it.
+pub(crate) fn with_payload_entry(payload: Value, key: &str, value: Value) -> Value {
+    let mut entries = match payload {
+        Value::Object(entries) => entries,
+        Value::Null => Map::new(),
+        payload => Map::from_iter([("payload".to_string(), payload)]),
+    };
+    entries.insert(key.to_string(), value);
+    Value::Object(entries)
+}
+
+/// `shortcut` in lower case with its modifiers in a fixed order, so
+/// `"Shift+Ctrl+C"` and `"ctrl+shift+c"` compare equal.
+pub fn normalize_shortcut(shortcut: &str) -> String {
+    const MODIFIERS: [&str; 4] = ["ctrl", "alt", "shift", "super"];
+
+    let mut modifiers = Vec::new();
+    let mut keys = Vec::new();
+    for part in shortcut.split('+').map(|part| part.trim().to_lowercase()) {
+        let part = match part.as_str() {
+            "control" => "ctrl".to_string(),
+            "option" => "alt".to_string(),
+            "cmd" | "command" | "meta" | "logo" | "win" => "super".to_string(),
+            _ => part,
+        };
+        match MODIFIERS.iter().position(|modifier| *modifier == part) {
+            Some(position) => modifiers.push(position),
+            None => keys.push(part),
+        }
+    }
+    modifiers.sort_unstable();
+    modifiers.dedup();
+
+    modifiers
+        .into_iter()
+        .map(|position| MODIFIERS[position].to_string())
+        .chain(keys)
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CommandType {
-    OpenApplication { path: String },
-    OpenFile { path: String },
-    OpenUrl { path: String },
-    RunScript { script: String, args: Vec<String> },
-    CopyToClipboard { text: String },
+    OpenApplication {
+        path: String,
+    },
+    OpenFile {
+        path: String,
+    },
+    OpenUrl {
+        path: String,
+    },
+    RunScript {
+        script: String,
+        args: Vec<String>,
+    },
+    CopyToClipboard {
+        text: String,
+    },
+    /// Replaces the text in the search bar.
+    SetQuery {
+        query: String,
+    },
+    /// Handled by the action `action_id` registered by an extension. The
+    /// host fills in `extension_id` with the extension that returned the
+    /// item, so extensions leave it out.
+    ExtensionCallback {
+        #[serde(default)]
+        extension_id: Uuid,
+        action_id: String,
+        #[serde(default)]
+        payload: serde_json::Value,
+    },
+    /// Opens the recent log output of the extensions.
+    ShowExtensionLogs,
+}
+
+impl CommandType {
+    /// The command with `values` for `arguments` filled in. Extension
+    /// callbacks get them by name as `arguments` in their payload, other
+    /// commands have `{name}` in their text replaced.
+    pub fn with_arguments(
+        &self,
+        arguments: &[Argument],
+        values: &[String],
+    ) -> anyhow::Result<Self> {
+        let value = |index: usize| values.get(index).map(String::as_str).unwrap_or_default();
+        if let Some(missing) = arguments
+            .iter()
+            .enumerate()
+            .find(|(index, argument)| argument.required && value(*index).trim().is_empty())
+        {
+            anyhow::bail!("{} is required", missing.1.placeholder());
+        }
+
+        let fill = |text: &str, encode: bool| {
+            arguments
+                .iter()
+                .enumerate()
+                .fold(text.to_string(), |text, (index, argument)| {
+                    let value = if encode {
+                        url::form_urlencoded::byte_serialize(value(index).as_bytes()).collect()
+                    } else {
+                        value(index).to_string()
+                    };
+                    text.replace(&format!("{{{}}}", argument.name), &value)
+                })
+        };
+
+        Ok(match self {
+            CommandType::OpenApplication { path } => CommandType::OpenApplication {
+                path: fill(path, false),
+            },
+            CommandType::OpenFile { path } => CommandType::OpenFile {
+                path: fill(path, false),
+            },
+            CommandType::OpenUrl { path } => CommandType::OpenUrl {
+                path: fill(path, true),
+            },
+            CommandType::RunScript { script, args } => CommandType::RunScript {
+                script: fill(script, false),
+                args: args.iter().map(|arg| fill(arg, false)).collect(),
+            },
+            CommandType::CopyToClipboard { text } => CommandType::CopyToClipboard {
+                text: fill(text, false),
+            },
+            CommandType::SetQuery { query } => CommandType::SetQuery {
+                query: fill(query, false),
+            },
+            CommandType::ExtensionCallback {
+                extension_id,
+                action_id,
+                payload,
+            } => {
+                let named = arguments
+                    .iter()
+                    .enumerate()
+                    .map(|(index, argument)| (argument.name.clone(), value(index).into()))
+                    .collect();
+                CommandType::ExtensionCallback {
+                    extension_id: *extension_id,
+                    action_id: action_id.clone(),
+                    payload: with_payload_entry(payload.clone(), "arguments", Value::Object(named)),
+                }
+            }
+            CommandType::ShowExtensionLogs => CommandType::ShowExtensionLogs,
+        })
+    }
 }
 
 #[async_trait]
@@ -32,22 +238,47 @@ pub trait CommandHandler: Send + Sync {
     fn can_handle(&self, command: &CommandType) -> bool;
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CommandResult {
     Success,
     ShowResults(Vec<Item>),
+    /// Show these items as a list of their own, e.g. the branches of the
+    /// chosen repository. Escape returns to the previous list.
+    PushList {
+        title: String,
+        items: Vec<Item>,
+    },
+    /// Show this item's preview on a view of its own, e.g. a whole note.
+    /// Escape returns to the previous view.
+    ShowDetail(Item),
+    /// Ask for input, which is sent to the form's submit command. Only
+    /// extensions ask for input, see [`Form::submit`].
+    ShowForm(Form),
     ShowNotification(String),
+    /// Put this text in the search bar.
+    SetQuery(String),
+    /// Show the log output of the extensions.
+    ShowExtensionLogs,
     Error(String),
 }
 
 #[derive(Default)]
 pub struct CommandRegistry {
-    handlers: HashMap<String, Box<dyn CommandHandler>>,
+    handlers: HashMap<String, Arc<dyn CommandHandler>>,
 }
 
 impl CommandRegistry {
     pub fn register_handler(&mut self, id: String, handler: Box<dyn CommandHandler>) {
-        self.handlers.insert(id, handler);
+        self.handlers.insert(id, Arc::from(handler));
+    }
+
+    /// The handler for `command`, so it can be executed without holding the
+    /// registry's lock.
+    pub fn handler_for(&self, command: &CommandType) -> Option<Arc<dyn CommandHandler>> {
+        self.handlers
+            .values()
+            .find(|handler| handler.can_handle(command))
+            .cloned()
     }
 
     pub async fn execute(
@@ -68,6 +299,84 @@ impl CommandRegistry {
     }
 }
 
+/// Hands [`CommandType::SetQuery`] back to the UI, which owns the search bar.
+pub(crate) struct SetQueryHandler;
+
+#[async_trait]
+impl CommandHandler for SetQueryHandler {
+    async fn execute(
+        &self,
+        command: CommandType,
+        _context: &AppContext,
+    ) -> anyhow::Result<CommandResult> {
+        match command {
+            CommandType::SetQuery { query } => Ok(CommandResult::SetQuery(query)),
+            command => anyhow::bail!("Not a query command: {:?}", command),
+        }
+    }
+
+    fn can_handle(&self, command: &CommandType) -> bool {
+        matches!(command, CommandType::SetQuery { .. })
+    }
+}
+
+/// Hands [`CommandType::ShowExtensionLogs`] back to the UI, which shows the
+/// logs.
+pub(crate) struct ExtensionLogsHandler;
+
+#[async_trait]
+impl CommandHandler for ExtensionLogsHandler {
+    async fn execute(
+        &self,
+        command: CommandType,
+        _context: &AppContext,
+    ) -> anyhow::Result<CommandResult> {
+        match command {
+            CommandType::ShowExtensionLogs => Ok(CommandResult::ShowExtensionLogs),
+            command => anyhow::bail!("Not a logs command: {:?}", command),
+        }
+    }
+
+    fn can_handle(&self, command: &CommandType) -> bool {
+        matches!(command, CommandType::ShowExtensionLogs)
+    }
+}
+
+/// Routes [`CommandType::ExtensionCallback`] commands back into the runtime
+/// of the extension that owns the action.
+pub(crate) struct ExtensionCallbackHandler;
+
+#[async_trait]
+impl CommandHandler for ExtensionCallbackHandler {
+    async fn execute(
+        &self,
+        command: CommandType,
+        context: &AppContext,
+    ) -> anyhow::Result<CommandResult> {
+        let CommandType::ExtensionCallback {
+            extension_id,
+            action_id,
+            payload,
+        } = command
+        else {
+            anyhow::bail!("Not an extension callback: {:?}", command);
+        };
+
+        let extension = context
+            .extension_manager
+            .read()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on extension manager"))?
+            .get(extension_id)
+            .ok_or_else(|| anyhow::anyhow!("Extension {extension_id} is not loaded"))?;
+
+        extension.run_action(&action_id, payload).await
+    }
+
+    fn can_handle(&self, command: &CommandType) -> bool {
+        matches!(command, CommandType::ExtensionCallback { .. })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -134,4 +443,73 @@ mod tests {
                 .contains("No handler found for command")
         );
     }
+
+    #[tokio::test]
+    async fn test_extension_callback_unknown_extension() {
+        let mut registry = CommandRegistry::default();
+        registry.register_handler(
+            "extension_callback".to_string(),
+            Box::new(ExtensionCallbackHandler),
+        );
+
+        let command: CommandType = serde_json::from_value(serde_json::json!({
+            "ExtensionCallback": { "action_id": "archive", "payload": { "id": 7 } }
+        }))
+        .expect("Parse extension callback");
+        assert!(registry.handler_for(&command).is_some());
+
+        let context = create_mock_context().await;
+        let result = registry.execute(command, &context).await;
+        assert!(result.unwrap_err().to_string().contains("is not loaded"));
+    }
+
+    #[test]
+    fn test_command_with_arguments() {
+        let arguments = [
+            Argument {
+                name: "to".to_string(),
+                placeholder: Some("language".to_string()),
+                required: true,
+            },
+            Argument {
+                name: "text".to_string(),
+                placeholder: None,
+                required: false,
+            },
+        ];
+        let values = split_arguments(" de  good morning ", arguments.len());
+        assert_eq!(values, ["de", "good morning"]);
+
+        let command = CommandType::OpenUrl {
+            path: "https://translate.example/?tl={to}&q={text}".to_string(),
+        };
+        assert_eq!(
+            command.with_arguments(&arguments, &values).unwrap(),
+            CommandType::OpenUrl {
+                path: "https://translate.example/?tl=de&q=good+morning".to_string(),
+            }
+        );
+
+        let callback = CommandType::ExtensionCallback {
+            extension_id: Uuid::nil(),
+            action_id: "translate".to_string(),
+            payload: Value::Null,
+        };
+        let CommandType::ExtensionCallback { payload, .. } =
+            callback.with_arguments(&arguments, &values).unwrap()
+        else {
+            panic!("Not an extension callback");
+        };
+        assert_eq!(payload["arguments"]["text"], "good morning");
+
+        let error = command.with_arguments(&arguments, &[]).unwrap_err();
+        assert_eq!(error.to_string(), "language is required");
+    }
+
+    #[test]
+    fn test_normalize_shortcut() {
+        assert_eq!(normalize_shortcut("Shift+Control+C"), "ctrl+shift+c");
+        assert_eq!(normalize_shortcut("cmd + enter"), "super+enter");
+        assert_eq!(normalize_shortcut("alt+1"), "alt+1");
+    }
 }
@@ -1,17 +1,378 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::Ok;
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub logging: LoggingConfig,
+    pub extensions: ExtensionsConfig,
+    /// Remapped provider keywords, from the keyword a provider declares to
+    /// the one typed instead. An empty keyword disables it.
+    pub keywords: HashMap<String, String>,
+    pub fallbacks: FallbacksConfig,
+    pub sections: SectionsConfig,
+    pub icons: IconsConfig,
+    pub preview: PreviewConfig,
+    pub theme: ThemeConfig,
+    pub window: WindowConfig,
+}
+
+impl AppConfig {
+    /// The configuration in `config.toml`, or the defaults when there is
+    /// none.
+    pub fn load() -> anyhow::Result<Self> {
+        let config_path = config_dir()?.join("config.toml");
+        if !config_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&config_path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Writes the configuration to `config.toml`.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let config_dir = config_dir()?;
+        std::fs::create_dir_all(&config_dir)?;
+        self.save_to(&config_dir.join("config.toml"))
+    }
+
+    /// Writes the settings that differ from those in the file at `path`
+    /// into it. Its comments and formatting are kept, and settings it leaves
+    /// at their defaults stay unset.
+    fn save_to(&self, path: &Path) -> anyhow::Result<()> {
+        let content = match std::fs::read_to_string(path) {
+            Result::Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut document: toml_edit::DocumentMut = content.parse()?;
+        let saved: AppConfig = toml::from_str(&content)?;
+
+        update_table(
+            document.as_table_mut(),
+            &toml::Table::try_from(saved)?,
+            &toml::Table::try_from(self)?,
+        )?;
+        std::fs::write(path, document.to_string())?;
+        Ok(())
+    }
+
+    /// The setting at `key`, a dotted path such as `window.width`.
+    pub fn get(&self, key: &str) -> anyhow::Result<toml::Value> {
+        let mut value = toml::Value::try_from(self)?;
+        for part in key.split('.') {
+            value = match value {
+                toml::Value::Table(mut table) => table.remove(part),
+                _ => None,
+            }
+            .ok_or_else(|| anyhow::anyhow!("Unknown setting '{key}'"))?;
+        }
+        Ok(value)
+    }
+
+    /// Changes the setting at `key` to `value`, written as in `config.toml`.
+    /// Values that aren't valid TOML are taken as strings, so `dark` works
+    /// as well as `"dark"`.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        let value = toml::from_str::<toml::Table>(&format!("value = {value}"))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(value.to_string()));
+
+        let mut root = toml::Table::try_from(&*self)?;
+        let (parents, name) = match key.rsplit_once('.') {
+            Some((parents, name)) => (Some(parents), name),
+            None => (None, key),
+        };
+        let mut table = &mut root;
+        for part in parents.into_iter().flat_map(|parents| parents.split('.')) {
+            table = match table.get_mut(part) {
+                Some(toml::Value::Table(nested)) => nested,
+                _ => anyhow::bail!("Unknown setting '{key}'"),
+            };
+        }
+        // Every field is serialized except unset options, so a new name is
+        // either one of those, an entry of a map, or unknown
+        let known = table.contains_key(name);
+        table.insert(name.to_string(), value);
+
+        let updated: AppConfig = root
+            .try_into()
+            .map_err(|e| anyhow::anyhow!("Invalid value for '{key}': {e}"))?;
+        // Settings serde doesn't know are dropped rather than rejected
+        if !known && updated.get(key).is_err() {
+            anyhow::bail!("Unknown setting '{key}'");
+        }
+
+        *self = updated;
+        Ok(())
+    }
+}
+
+/// Changes the settings in `document` from their `saved` values to the
+/// `current` ones, leaving the rest of it as it is.
+fn update_table(
+    document: &mut toml_edit::Table,
+    saved: &toml::Table,
+    current: &toml::Table,
+) -> anyhow::Result<()> {
+    for (key, value) in current {
+        let old = saved.get(key);
+        if old == Some(value) {
+            continue;
+        }
+
+        match (value, old, document.get_mut(key)) {
+            (
+                toml::Value::Table(nested),
+                Some(toml::Value::Table(old)),
+                Some(toml_edit::Item::Table(table)),
+            ) => update_table(table, old, nested)?,
+            // Sections the file doesn't have yet
+            (toml::Value::Table(nested), old, _) => {
+                let old = match old {
+                    Some(toml::Value::Table(old)) => old.clone(),
+                    _ => toml::Table::new(),
+                };
+                let mut table = toml_edit::Table::new();
+                update_table(&mut table, &old, nested)?;
+                document.insert(key, toml_edit::Item::Table(table));
+            }
+            (value, _, _) => {
+                let value: toml_edit::Value = value.to_string().parse()?;
+                document.insert(key, toml_edit::value(value));
+            }
+        }
+    }
+    // Entries of maps, like remapped keywords, can be removed
+    for key in saved.keys().filter(|key| !current.contains_key(*key)) {
+        document.remove(key);
+    }
+    Ok(())
+}
+
+/// Where the configuration and user themes live.
+pub fn config_dir() -> anyhow::Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?
+        .join("cognito"))
+}
+
+/// Where extensions are installed, one directory per extension.
+pub fn extensions_dir() -> anyhow::Result<PathBuf> {
+    Ok(config_dir()?.join("extensions"))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AppConfig {}
+#[serde(default)]
+pub struct LoggingConfig {
+    /// One of `off`, `error`, `warn`, `info`, `debug` or `trace`.
+    pub level: String,
+    pub max_file_size_kb: u64,
+    /// Number of rotated log files kept next to the current one.
+    pub max_files: usize,
+}
 
-impl Default for AppConfig {
+impl Default for LoggingConfig {
     fn default() -> Self {
-        Self {}
+        Self {
+            level: "info".to_string(),
+            max_file_size_kb: 1024,
+            max_files: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtensionsConfig {
+    /// Shortest interval at which an extension's background jobs may run.
+    pub min_job_interval_secs: u64,
+    /// Pause background jobs while the launcher is hidden. Jobs can opt out
+    /// with `runWhileHidden`.
+    pub suspend_jobs_while_hidden: bool,
+}
+
+impl ExtensionsConfig {
+    pub fn min_job_interval(&self) -> Duration {
+        Duration::from_secs(self.min_job_interval_secs)
     }
 }
 
+impl Default for ExtensionsConfig {
+    fn default() -> Self {
+        Self {
+            min_job_interval_secs: 30,
+            suspend_jobs_while_hidden: true,
+        }
+    }
+}
+
+/// Fallbacks are named `<extension>:<fallback id>`, e.g. `web:search`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FallbacksConfig {
+    /// Fallbacks listed here come first, in this order. The others follow in
+    /// the order their extensions were loaded.
+    pub order: Vec<String>,
+    pub disabled: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SectionsConfig {
+    /// Items shown per section before "show more". 0 shows everything.
+    pub default_limit: usize,
+    /// Limits of single sections, by section id: `ext:<name>` for the results
+    /// of an extension, or `keywords`, `commands` and `fallbacks`.
+    pub limits: HashMap<String, usize>,
+}
+
+impl SectionsConfig {
+    pub fn limit(&self, section: &str) -> Option<usize> {
+        let limit = self
+            .limits
+            .get(section)
+            .copied()
+            .unwrap_or(self.default_limit);
+        (limit > 0).then_some(limit)
+    }
+}
+
+impl Default for SectionsConfig {
+    fn default() -> Self {
+        Self {
+            default_limit: 5,
+            limits: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IconsConfig {
+    /// Freedesktop icon theme to look icon names up in, before `hicolor`.
+    pub theme: Option<String>,
+    /// Size icons are drawn at, in pixels.
+    pub size: u16,
+}
+
+impl Default for IconsConfig {
+    fn default() -> Self {
+        Self {
+            theme: None,
+            size: 32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreviewConfig {
+    /// Show the preview of the selected item next to the results.
+    pub enabled: bool,
+    /// Width of the preview pane, in pixels.
+    pub width: u16,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            width: 320,
+        }
+    }
+}
+
+/// Themes are named after their file in the `themes` config directory,
+/// without `.toml`, or `light` and `dark` for the built-in ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub name: String,
+    /// Use `light` or `dark` as the desktop prefers, instead of `name`.
+    pub follow_system: bool,
+    pub light: String,
+    pub dark: String,
+}
+
+impl ThemeConfig {
+    /// The theme to use, given whether the desktop prefers dark colors.
+    pub fn name(&self, prefers_dark: Option<bool>) -> &str {
+        match prefers_dark {
+            Some(true) if self.follow_system => &self.dark,
+            Some(false) if self.follow_system => &self.light,
+            _ => &self.name,
+        }
+    }
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            name: "dark".to_string(),
+            follow_system: false,
+            light: "light".to_string(),
+            dark: "dark".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// Width of the window, in pixels.
+    pub width: u16,
+    /// Result rows shown before the list scrolls.
+    pub max_rows: usize,
+    /// Distance from the top of the monitor to the window, as a fraction of
+    /// the monitor's height.
+    pub vertical_offset: f32,
+    pub monitor: MonitorChoice,
+    /// Fit the window to the results, instead of always leaving room for
+    /// `max_rows` of them.
+    pub grow_with_results: bool,
+    pub hide_on_focus_loss: bool,
+    /// Show the query and view the launcher was hidden with when it is shown
+    /// again, instead of an empty search.
+    pub keep_query: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 750,
+            max_rows: 8,
+            vertical_offset: 0.25,
+            monitor: MonitorChoice::default(),
+            grow_with_results: true,
+            hide_on_focus_loss: true,
+            keep_query: false,
+        }
+    }
+}
+
+/// The monitor the window opens on. Only followed where windows can place
+/// themselves, i.e. on X11.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MonitorChoice {
+    #[default]
+    Primary,
+    /// The monitor under the mouse cursor.
+    Cursor,
+    /// The monitor of the focused window.
+    Focused,
+}
+
 #[derive(Debug, Clone)]
 pub struct ConfigManager {
     config: AppConfig,
@@ -20,19 +381,10 @@ pub struct ConfigManager {
 
 impl ConfigManager {
     pub(crate) fn new() -> anyhow::Result<Self> {
-        let config_dir = dirs::config_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?
-            .join("cognito");
-
+        let config_dir = config_dir()?;
         std::fs::create_dir_all(&config_dir)?;
         let config_path = config_dir.join("config.toml");
-
-        let config = if config_path.exists() {
-            let content = std::fs::read_to_string(&config_path)?;
-            toml::from_str(&content)?
-        } else {
-            AppConfig::default()
-        };
+        let config = AppConfig::load()?;
 
         Ok(Self {
             config,
@@ -53,8 +405,76 @@ impl ConfigManager {
     }
 
     pub fn save(&self) -> anyhow::Result<()> {
-        let content = toml::to_string_pretty(&self.config)?;
-        std::fs::write(&self.config_path, content)?;
-        Ok(())
+        self.config.save_to(&self.config_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_and_set_settings() {
+        let mut config = AppConfig::default();
+        config.set("window.width", "900").unwrap();
+        config.set("theme.name", "light").unwrap();
+        config.set("keywords.gh", "\"g\"").unwrap();
+
+        assert_eq!(config.window.width, 900);
+        assert_eq!(config.get("theme.name").unwrap().as_str(), Some("light"));
+        assert_eq!(config.keywords["gh"], "g");
+
+        assert!(config.set("window.width", "wide").is_err());
+        assert!(config.set("window.colour", "red").is_err());
+        assert!(config.get("window.width.px").is_err());
+        assert_eq!(config.window.width, 900);
+    }
+
+    #[test]
+    fn test_set_float_setting() {
+        let mut config = AppConfig::default();
+        config.set("window.vertical_offset", "0.3").unwrap();
+        assert!((config.window.vertical_offset - 0.3).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_set_unknown_setting() {
+        let mut config = AppConfig::default();
+        assert!(config.set("window.colour", "red").is_err());
+        assert!(config.set("colour", "red").is_err());
+        assert!(config.get("window.colour").is_err());
+
+        config.set("icons.theme", "Papirus").unwrap();
+        assert_eq!(config.icons.theme.as_deref(), Some("Papirus"));
+    }
+
+    #[test]
+    fn test_save_keeps_the_file_as_written() {
+        let dir = std::env::temp_dir().join(format!("cognito-config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(
+            &path,
+            "# Wider than usual\n[window]\nwidth = 900 # pixels\n\n[keywords]\ngh = \"g\"\n",
+        )
+        .unwrap();
+
+        let mut config: AppConfig =
+            toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        config.set("window.max_rows", "5").unwrap();
+        config.set("theme.name", "light").unwrap();
+        config.keywords.clear();
+        config.save_to(&path).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# Wider than usual\n[window]\nwidth = 900 # pixels\n"));
+        assert!(content.contains("max_rows = 5"));
+        assert!(content.contains("[theme]\nname = \"light\""));
+        assert!(!content.contains("gh ="));
+        // Settings left at their defaults aren't written out
+        assert!(!content.contains("[preview]"));
+        assert!(!content.contains("follow_system"));
+
+        let _ = std::fs::remove_dir_all(dir);
     }
 }
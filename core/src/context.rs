@@ -1,8 +1,16 @@
 use std::sync::{Arc, RwLock};
 
+use uuid::Uuid;
+
 use crate::{
-    commands::CommandRegistry, config::ConfigManager, events::EventBus,
-    extensions::ExtensionManager, state::AppState,
+    commands::{Argument, CommandRegistry},
+    config::{AppConfig, ConfigManager},
+    events::EventBus,
+    extensions::ExtensionManager,
+    fallbacks::FallbackRegistry,
+    icons::IconResolver,
+    keywords::KeywordRegistry,
+    state::{AppMode, AppState, Item, Section, SelectionMove},
 };
 
 #[derive(Clone)]
@@ -12,16 +20,28 @@ pub struct AppContext {
     pub config: Arc<RwLock<ConfigManager>>,
     pub extension_manager: Arc<RwLock<ExtensionManager>>,
     pub command_registry: Arc<RwLock<CommandRegistry>>,
+    pub keywords: Arc<RwLock<KeywordRegistry>>,
+    pub fallbacks: Arc<RwLock<FallbackRegistry>>,
+    pub icons: Arc<IconResolver>,
 }
 
 impl AppContext {
     pub(crate) fn new() -> anyhow::Result<Self> {
+        let config = ConfigManager::new()?;
+        let icons = IconResolver::new(
+            config.get().icons.clone(),
+            dirs::cache_dir().map(|dir| dir.join("cognito").join("icons")),
+        );
+
         Ok(Self {
             state: Arc::new(RwLock::new(AppState::default())),
             event_bus: EventBus::new(),
-            config: Arc::new(RwLock::new(ConfigManager::new()?)),
+            config: Arc::new(RwLock::new(config)),
             extension_manager: Arc::new(RwLock::new(ExtensionManager::new()?)),
             command_registry: Arc::new(RwLock::new(CommandRegistry::default())),
+            keywords: Arc::new(RwLock::new(KeywordRegistry::default())),
+            fallbacks: Arc::new(RwLock::new(FallbackRegistry::default())),
+            icons: Arc::new(icons),
         })
     }
 
@@ -32,22 +52,166 @@ impl AppContext {
         }
     }
 
+    pub fn get_sections(&self) -> Vec<Section> {
+        match self.state.read() {
+            Ok(state) => state.sections.clone(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn get_config(&self) -> AppConfig {
+        match self.config.read() {
+            Ok(config) => config.get().clone(),
+            Err(_) => AppConfig::default(),
+        }
+    }
+
+    /// Stores `query` as the current query. The previous results are cleared
+    /// only when it actually changed, so handling the same query twice (once
+    /// by the UI and once from the event bus) does not discard results.
     pub fn handle_query(&self, query: String) -> anyhow::Result<()> {
-        {
-            let mut state = self
-                .state
-                .write()
-                .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on state"))?;
-            state.query = query.clone();
+        let mut state = self
+            .state
+            .write()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on state"))?;
+
+        if state.query != query {
+            state.close_actions();
+            state.arguments = None;
+            state.query = query;
+            state.selected = 0;
+            // Pushed lists are filtered right away
+            if !state.filter_list() {
+                state.sections = Vec::new();
+            }
         }
 
-        {
-            let mut state = self
-                .state
-                .write()
-                .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on state"))?;
-            state.items = Vec::new();
+        Ok(())
+    }
+
+    /// Lifts the limit of the section `id` until the query changes.
+    pub fn expand_section(&self, id: &str) -> anyhow::Result<()> {
+        self.update_state(|state| state.expand_section(id))
+    }
+
+    pub fn get_selected(&self) -> usize {
+        match self.state.read() {
+            Ok(state) => state.selected,
+            Err(_) => 0,
+        }
+    }
+
+    pub fn get_selected_item(&self) -> Option<Item> {
+        self.state.read().ok()?.selected_item().cloned()
+    }
+
+    pub fn select(&self, index: usize) -> anyhow::Result<()> {
+        self.update_state(|state| state.select(index))
+    }
+
+    pub fn move_selection(&self, movement: SelectionMove) -> anyhow::Result<()> {
+        self.update_state(|state| state.move_selection(movement))
+    }
+
+    pub fn get_mode(&self) -> AppMode {
+        match self.state.read() {
+            Ok(state) => state.mode.clone(),
+            Err(_) => AppMode::Search,
         }
+    }
+
+    /// Opens the action panel of the selected item, see
+    /// [`AppState::open_actions`].
+    pub fn open_actions(&self) -> anyhow::Result<bool> {
+        let mut opened = false;
+        self.update_state(|state| opened = state.open_actions())?;
+        Ok(opened)
+    }
+
+    pub fn close_actions(&self) -> anyhow::Result<()> {
+        self.update_state(AppState::close_actions)
+    }
+
+    pub fn set_action_filter(&self, filter: String) -> anyhow::Result<()> {
+        self.update_state(|state| state.set_action_filter(filter))
+    }
+
+    pub fn get_selected_action(&self) -> Option<(Uuid, usize)> {
+        self.state.read().ok()?.selected_action()
+    }
+
+    /// Starts typing the arguments of the selected item, see
+    /// [`AppState::open_arguments`].
+    pub fn open_arguments(&self) -> anyhow::Result<bool> {
+        let mut opened = false;
+        self.update_state(|state| opened = state.open_arguments())?;
+        Ok(opened)
+    }
+
+    pub fn close_arguments(&self) -> anyhow::Result<()> {
+        self.update_state(|state| state.arguments = None)
+    }
+
+    pub fn set_argument(&self, index: usize, value: String) -> anyhow::Result<()> {
+        self.update_state(|state| state.set_argument(index, value))
+    }
+
+    /// The arguments of the selected item and the values typed for them,
+    /// while they are typed.
+    pub fn get_arguments(&self) -> Option<(Vec<Argument>, Vec<String>)> {
+        let state = self.state.read().ok()?;
+        let item = state.arguments_item()?;
+        let values = state.arguments.as_ref()?.values.clone();
+        Some((item.arguments.clone(), values))
+    }
+
+    /// Placeholders of the arguments of the keyword `query` starts with that
+    /// are still to be typed.
+    pub fn get_argument_hint(&self, query: &str) -> Vec<String> {
+        match self.keywords.read() {
+            Ok(keywords) => keywords
+                .missing_arguments(query)
+                .into_iter()
+                .map(|argument| argument.placeholder().to_string())
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Shows `mode` on top of the current view, see
+    /// [`AppState::push_view`].
+    pub fn push_view(&self, mode: AppMode) -> anyhow::Result<()> {
+        self.update_state(|state| state.push_view(mode))
+    }
+
+    /// Returns to the previous view. Returns `false` at the root.
+    pub fn pop(&self) -> anyhow::Result<bool> {
+        let mut popped = false;
+        self.update_state(|state| popped = state.pop())?;
+        Ok(popped)
+    }
+
+    pub fn pop_to_root(&self) -> anyhow::Result<()> {
+        self.update_state(AppState::pop_to_root)
+    }
+
+    pub fn get_breadcrumbs(&self) -> Vec<String> {
+        match self.state.read() {
+            Ok(state) => state
+                .breadcrumbs()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn update_state(&self, update: impl FnOnce(&mut AppState)) -> anyhow::Result<()> {
+        let mut state = self
+            .state
+            .write()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on state"))?;
+        update(&mut state);
 
         Ok(())
     }
@@ -0,0 +1,197 @@
+use std::{
+    fs::{File, TryLockError},
+    io::{ErrorKind, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use futures::{Stream, StreamExt, stream};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::events::AppEvent;
+
+/// What a later invocation asks of the running instance. Sent over the
+/// control socket as one line of JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DaemonCommand {
+    /// Shows the launcher, searching for `query` when given.
+    Show {
+        query: Option<String>,
+    },
+    Toggle,
+    /// Reloads the installed extensions.
+    ReloadExtensions,
+    Quit,
+}
+
+impl From<DaemonCommand> for AppEvent {
+    fn from(command: DaemonCommand) -> Self {
+        match command {
+            DaemonCommand::Show { query } => AppEvent::ShowLauncher { query },
+            DaemonCommand::Toggle => AppEvent::ToggleLauncher,
+            DaemonCommand::ReloadExtensions => AppEvent::ReloadExtensions,
+            DaemonCommand::Quit => AppEvent::Quit,
+        }
+    }
+}
+
+/// The control socket of the running instance, `cognito.sock` in
+/// `$XDG_RUNTIME_DIR`.
+pub fn socket_path() -> anyhow::Result<PathBuf> {
+    Ok(dirs::runtime_dir()
+        .ok_or_else(|| anyhow::anyhow!("XDG_RUNTIME_DIR is not set"))?
+        .join("cognito.sock"))
+}
+
+/// Sends `command` to the running instance. Returns `false` when there is
+/// none.
+pub fn send(command: &DaemonCommand) -> anyhow::Result<bool> {
+    let mut stream = match UnixStream::connect(socket_path()?) {
+        Ok(stream) => stream,
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+            return Ok(false);
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut line = serde_json::to_string(command)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(true)
+}
+
+/// The control socket, owned by the first instance. The socket file is
+/// removed when it is dropped.
+#[derive(Debug)]
+pub struct Daemon {
+    listener: Arc<UnixListener>,
+    path: PathBuf,
+    /// Lock on the file next to the socket, held for as long as the socket
+    /// is owned.
+    _lock: File,
+}
+
+impl Daemon {
+    /// Takes over the control socket. Returns `None` when another instance
+    /// owns it, or is about to. A socket left behind by one that crashed is
+    /// replaced.
+    pub fn bind() -> anyhow::Result<Option<Self>> {
+        Self::bind_at(socket_path()?)
+    }
+
+    /// Instances starting at the same time take turns through a lock file,
+    /// so one can't replace the socket the other just bound.
+    fn bind_at(path: PathBuf) -> anyhow::Result<Option<Self>> {
+        let lock = File::create(path.with_extension("lock"))?;
+        match lock.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        if UnixStream::connect(&path).is_ok() {
+            return Ok(None);
+        }
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Some(Self {
+            listener: Arc::new(listener),
+            path,
+            _lock: lock,
+        }))
+    }
+
+    /// Commands sent to the socket, as they arrive. Connections that don't
+    /// send a valid command are logged and dropped. Nothing is set up until
+    /// the stream is first polled.
+    pub fn commands(&self) -> impl Stream<Item = DaemonCommand> + Send + 'static {
+        let listener = Arc::clone(&self.listener);
+
+        // Tokio listeners must be created inside the runtime
+        let listener = stream::once(async move {
+            listener
+                .try_clone()
+                .and_then(tokio::net::UnixListener::from_std)
+        })
+        .filter_map(|listener| async move {
+            listener
+                .inspect_err(|e| warn!("Failed to listen on the control socket: {e}"))
+                .ok()
+        });
+
+        listener.flat_map(|listener| {
+            stream::unfold(listener, |listener| async move {
+                loop {
+                    let connection = match listener.accept().await {
+                        Ok((connection, _)) => connection,
+                        Err(e) => {
+                            warn!("Failed to accept a control connection: {e}");
+                            continue;
+                        }
+                    };
+                    match read_command(connection).await {
+                        Ok(command) => return Some((command, listener)),
+                        Err(e) => warn!("Invalid control command: {e}"),
+                    }
+                }
+            })
+        })
+    }
+}
+
+async fn read_command(connection: tokio::net::UnixStream) -> anyhow::Result<DaemonCommand> {
+    let mut line = String::new();
+    BufReader::new(connection).read_line(&mut line).await?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_wire_format() {
+        let command = DaemonCommand::Show {
+            query: Some("foo".to_string()),
+        };
+        let line = serde_json::to_string(&command).unwrap();
+        assert_eq!(line, r#"{"Show":{"query":"foo"}}"#);
+        assert_eq!(
+            serde_json::from_str::<DaemonCommand>(&line).unwrap(),
+            command
+        );
+        assert_eq!(
+            AppEvent::from(DaemonCommand::Toggle),
+            AppEvent::ToggleLauncher
+        );
+    }
+
+    #[test]
+    fn test_one_instance_owns_the_socket() {
+        let dir = std::env::temp_dir().join(format!("cognito-daemon-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cognito.sock");
+
+        let daemon = Daemon::bind_at(path.clone()).unwrap().unwrap();
+        assert!(Daemon::bind_at(path.clone()).unwrap().is_none());
+
+        // The lock goes with the instance, and the socket file with it
+        drop(daemon);
+        assert!(!path.exists());
+        assert!(Daemon::bind_at(path.clone()).unwrap().is_some());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
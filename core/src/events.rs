@@ -2,25 +2,111 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 use uuid::Uuid;
 
-use crate::state::Item;
+use crate::{
+    commands::CommandResult,
+    state::{Preview, Section, SelectionMove},
+};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AppEvent {
     // Core events
     QueryChanged(String),
-    ResultsUpdated(Vec<Item>),
+    ResultsUpdated(Vec<Section>),
     ItemActivated(Uuid, usize),
+    /// "Show more" was chosen in the section with this id.
+    ExpandSection(String),
+    MoveSelection(SelectionMove),
+    /// Activates the default action of the visible item at this index.
+    QuickSelect(usize),
+    /// Activates the default action of the selected item.
+    ActivateSelected,
+    /// The result of the action run by `ItemActivated`.
+    CommandCompleted(CommandResult),
+    /// Escape was pressed: clears the query, or hides the launcher when it
+    /// is empty.
+    Dismiss,
+    /// Opens the action panel of the selected item, or closes it.
+    ToggleActions,
+    ActionFilterChanged(String),
+    /// Keys with a modifier were pressed, as `normalize_shortcut` returns
+    /// them. Runs the selected item's action with this shortcut.
+    Shortcut(String),
+    /// Backspace was pressed. Returns to the previous view when the query
+    /// is empty, or from views without one when no text field `captured`
+    /// the key.
+    NavigateBack {
+        captured: bool,
+    },
+    /// The current view was replaced, e.g. a list was pushed or popped.
+    ViewChanged,
+    /// Tab was pressed, backwards with Shift. Moves through the arguments of
+    /// the selected item, or toggles the action panel when it takes none.
+    NextArgument {
+        backward: bool,
+    },
+    /// The argument at this index of the selected item was typed.
+    ArgumentChanged(usize, String),
+    /// A link in a preview was clicked.
+    OpenLink(String),
+    /// The file shown by the preview of an item was read, as the preview
+    /// to show instead, or the reason it can't be shown.
+    PreviewLoaded {
+        item_id: Uuid,
+        preview: Result<Preview, String>,
+    },
+    /// The result list was scrolled, or resized.
+    ResultsScrolled {
+        offset: f32,
+        height: f32,
+    },
 
     // Mode Events
     ExitToSearch,
+    ShowExtensionLogs(String),
 
     // System Events
     ApplicationStarted,
+    /// The application is quitting, not just hiding the launcher.
     ApplicationClosing,
+    LauncherShown,
+    LauncherHidden,
+    /// Asks for the launcher to be shown and focused, searching for `query`
+    /// when given.
+    ShowLauncher {
+        query: Option<String>,
+    },
+    /// Asks for the launcher to be hidden, e.g. when its window is closed.
+    HideLauncher,
+    /// Shows the launcher when it is hidden, hides it otherwise.
+    ToggleLauncher,
+    /// The launcher window lost focus.
+    FocusLost,
+    /// Asks for the installed extensions to be loaded again.
+    ReloadExtensions,
+    /// Asks the application to quit.
+    Quit,
+    /// The desktop switched between light and dark colors, or no longer
+    /// prefers either.
+    ColorSchemeChanged {
+        dark: Option<bool>,
+    },
+
+    // Extension Events
+    /// The items an extension's background jobs contribute to the root list
+    /// changed. Carries the extension's name.
+    ExtensionItemsChanged(String),
+    /// Custom event published by an extension. `name` is namespaced with the
+    /// publishing extension's name, e.g. `"github:pull-requests-changed"`.
+    ExtensionEvent {
+        source: String,
+        name: String,
+        payload: serde_json::Value,
+    },
 }
 
 pub type EventSender = broadcast::Sender<AppEvent>;
 pub type EventReceiver = broadcast::Receiver<AppEvent>;
+pub use broadcast::error::RecvError;
 
 #[derive(Debug, Clone)]
 pub struct EventBus {
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use deno_core::{ModuleSpecifier, SourceCodeCacheInfo};
+use sha2::{Digest, Sha256};
+
+/// On-disk cache of the V8 code cache of extension modules, keyed by a hash
+/// of the module's specifier and source. V8 rejects data it can't use (e.g.
+/// after an upgrade), in which case it is regenerated and overwritten.
+pub(crate) struct CodeCache {
+    dir: Option<PathBuf>,
+}
+
+impl CodeCache {
+    pub(crate) fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir }
+    }
+
+    pub(crate) fn get(&self, specifier: &ModuleSpecifier, code: &str) -> SourceCodeCacheInfo {
+        let hash = source_hash(specifier, code);
+        let data = self
+            .path(hash)
+            .and_then(|path| std::fs::read(path).ok())
+            .map(Into::into);
+
+        SourceCodeCacheInfo { hash, data }
+    }
+
+    pub(crate) fn set(&self, hash: u64, data: &[u8]) -> anyhow::Result<()> {
+        let (Some(dir), Some(path)) = (&self.dir, self.path(hash)) else {
+            return Ok(());
+        };
+
+        std::fs::create_dir_all(dir)?;
+        // Write to a temporary file first so a concurrent reader never sees
+        // partial data
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, data)?;
+        std::fs::rename(partial, path)?;
+
+        Ok(())
+    }
+
+    fn path(&self, hash: u64) -> Option<PathBuf> {
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{hash:016x}.bin")))
+    }
+}
+
+fn source_hash(specifier: &ModuleSpecifier, code: &str) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(specifier.as_str());
+    hasher.update([0]);
+    hasher.update(code);
+
+    let digest = hasher.finalize();
+    u64::from_le_bytes(digest[..8].try_into().expect("Digest is 32 bytes long"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_cache_round_trip() {
+        let dir = std::env::temp_dir().join(format!("cognito-code-cache-{}", uuid::Uuid::new_v4()));
+        let cache = CodeCache::new(Some(dir.clone()));
+        let specifier = ModuleSpecifier::parse("file:///extensions/example/main.js").unwrap();
+
+        let missing = cache.get(&specifier, "export const a = 1;");
+        assert!(missing.data.is_none());
+
+        cache.set(missing.hash, b"cached").unwrap();
+        let found = cache.get(&specifier, "export const a = 1;");
+        assert_eq!(found.hash, missing.hash);
+        assert_eq!(found.data.as_deref(), Some(&b"cached"[..]));
+
+        // Changing the source changes the key
+        assert!(cache.get(&specifier, "export const a = 2;").data.is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
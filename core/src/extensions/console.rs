@@ -0,0 +1,74 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use deno_core::{OpState, op2};
+use log::Level;
+
+/// How many entries are kept per extension for display in the UI.
+const MAX_RECENT_ENTRIES: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub timestamp: SystemTime,
+    pub level: Level,
+    pub message: String,
+}
+
+/// Log of a single extension. Entries are forwarded to the `log` crate with
+/// the extension name as target, and the most recent ones are kept in memory.
+#[derive(Debug, Clone)]
+pub(crate) struct ExtensionLog {
+    name: Arc<str>,
+    entries: Arc<Mutex<VecDeque<LogEntry>>>,
+}
+
+impl ExtensionLog {
+    pub(crate) fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            entries: Default::default(),
+        }
+    }
+
+    pub(crate) fn push(&self, level: Level, message: String) {
+        log::log!(target: &*self.name, level, "{message}");
+
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() == MAX_RECENT_ENTRIES {
+                entries.pop_front();
+            }
+            entries.push_back(LogEntry {
+                timestamp: SystemTime::now(),
+                level,
+                message,
+            });
+        }
+    }
+
+    pub(crate) fn entries(&self) -> Vec<LogEntry> {
+        match self.entries.lock() {
+            Ok(entries) => entries.iter().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+#[op2(fast)]
+pub(crate) fn op_cognito_log(
+    state: &mut OpState,
+    #[string] level: &str,
+    #[string] message: String,
+) {
+    let level = match level {
+        "error" => Level::Error,
+        "warn" => Level::Warn,
+        "debug" => Level::Debug,
+        "trace" => Level::Trace,
+        _ => Level::Info,
+    };
+
+    state.borrow::<ExtensionLog>().push(level, message);
+}
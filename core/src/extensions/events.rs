@@ -0,0 +1,198 @@
+use deno_core::{OpState, op2, v8};
+use deno_error::JsErrorBox;
+use serde::Serialize;
+
+use crate::events::{AppEvent, EventBus};
+
+/// Access to the application's event bus from an extension's runtime.
+#[derive(Debug, Clone)]
+pub(crate) struct EventAccess {
+    /// Name of the extension, used to namespace the events it publishes.
+    pub(crate) name: String,
+    pub(crate) bus: EventBus,
+}
+
+/// Handlers registered through `cognito.events.on`.
+#[derive(Default)]
+struct Subscriptions {
+    next_id: u32,
+    handlers: Vec<Subscription>,
+}
+
+struct Subscription {
+    id: u32,
+    filter: String,
+    handler: v8::Global<v8::Function>,
+}
+
+/// An `AppEvent` as seen by extensions. Only a subset of the application's
+/// events is delivered to them, see [`ExtensionEvent::from_app_event`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct ExtensionEvent {
+    #[serde(rename = "type")]
+    pub(crate) kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) source: Option<String>,
+    pub(crate) payload: serde_json::Value,
+}
+
+impl ExtensionEvent {
+    /// Translates `event` for the extension called `receiver`. Returns `None`
+    /// for events extensions can't subscribe to, and for the receiver's own
+    /// custom events.
+    pub(crate) fn from_app_event(event: &AppEvent, receiver: &str) -> Option<Self> {
+        let (kind, source, payload) = match event {
+            AppEvent::QueryChanged(query) => {
+                ("queryChanged".to_string(), None, query.clone().into())
+            }
+            AppEvent::ItemActivated(item_id, action_index) => (
+                "itemActivated".to_string(),
+                None,
+                serde_json::json!({ "itemId": item_id, "actionIndex": action_index }),
+            ),
+            AppEvent::ApplicationStarted => (
+                "applicationStarted".to_string(),
+                None,
+                serde_json::Value::Null,
+            ),
+            AppEvent::ApplicationClosing => (
+                "applicationClosing".to_string(),
+                None,
+                serde_json::Value::Null,
+            ),
+            AppEvent::LauncherShown => ("launcherShown".to_string(), None, serde_json::Value::Null),
+            AppEvent::LauncherHidden => {
+                ("launcherHidden".to_string(), None, serde_json::Value::Null)
+            }
+            AppEvent::ExtensionEvent {
+                source,
+                name,
+                payload,
+            } if source != receiver => (name.clone(), Some(source.clone()), payload.clone()),
+            _ => return None,
+        };
+
+        Some(Self {
+            kind,
+            source,
+            payload,
+        })
+    }
+}
+
+/// Whether a subscription to `filter` receives events of type `kind`. Filters
+/// are either an exact type, `"<namespace>:*"` for every custom event of one
+/// extension, or `"*"` for everything.
+fn filter_matches(filter: &str, kind: &str) -> bool {
+    match filter.strip_suffix('*') {
+        Some("") => true,
+        Some(prefix) if prefix.ends_with(':') => kind.starts_with(prefix),
+        _ => filter == kind,
+    }
+}
+
+/// The handlers subscribed to events of type `kind`, in registration order.
+pub(crate) fn handlers_for(state: &OpState, kind: &str) -> Vec<v8::Global<v8::Function>> {
+    state
+        .try_borrow::<Subscriptions>()
+        .map(|subscriptions| {
+            subscriptions
+                .handlers
+                .iter()
+                .filter(|subscription| filter_matches(&subscription.filter, kind))
+                .map(|subscription| subscription.handler.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[op2]
+pub(crate) fn op_cognito_subscribe(
+    state: &mut OpState,
+    #[string] filter: String,
+    #[global] handler: v8::Global<v8::Function>,
+) -> u32 {
+    if !state.has::<Subscriptions>() {
+        state.put(Subscriptions::default());
+    }
+
+    let subscriptions = state.borrow_mut::<Subscriptions>();
+    let id = subscriptions.next_id;
+    subscriptions.next_id += 1;
+    subscriptions.handlers.push(Subscription {
+        id,
+        filter,
+        handler,
+    });
+
+    id
+}
+
+#[op2(fast)]
+pub(crate) fn op_cognito_unsubscribe(state: &mut OpState, id: u32) {
+    if let Some(subscriptions) = state.try_borrow_mut::<Subscriptions>() {
+        subscriptions
+            .handlers
+            .retain(|subscription| subscription.id != id);
+    }
+}
+
+#[op2]
+pub(crate) fn op_cognito_publish(
+    state: &mut OpState,
+    #[string] name: String,
+    #[serde] payload: serde_json::Value,
+) -> Result<(), JsErrorBox> {
+    if name.is_empty() || name.contains(':') {
+        return Err(JsErrorBox::type_error(format!(
+            "Invalid event name '{name}': names must be non-empty and must not contain ':'"
+        )));
+    }
+
+    let access = state.borrow::<EventAccess>();
+    // Publishing fails only when nobody is listening, which is not an error
+    // for the extension
+    let _ = access.bus.publish(AppEvent::ExtensionEvent {
+        source: access.name.clone(),
+        name: format!("{}:{}", access.name, name),
+        payload,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_matches() {
+        assert!(filter_matches("queryChanged", "queryChanged"));
+        assert!(!filter_matches("queryChanged", "itemActivated"));
+        assert!(filter_matches("*", "applicationStarted"));
+        assert!(filter_matches("github:*", "github:refreshed"));
+        assert!(!filter_matches("github:*", "gitlab:refreshed"));
+        assert!(!filter_matches("git*", "github:refreshed"));
+    }
+
+    #[test]
+    fn test_extension_event_translation() {
+        let event = AppEvent::ExtensionEvent {
+            source: "github".to_string(),
+            name: "github:refreshed".to_string(),
+            payload: serde_json::json!({ "count": 3 }),
+        };
+
+        let translated = ExtensionEvent::from_app_event(&event, "mail").unwrap();
+        assert_eq!(translated.kind, "github:refreshed");
+        assert_eq!(translated.source.as_deref(), Some("github"));
+        assert_eq!(translated.payload["count"], 3);
+
+        // Extensions don't receive their own events
+        assert_eq!(ExtensionEvent::from_app_event(&event, "github"), None);
+        assert_eq!(
+            ExtensionEvent::from_app_event(&AppEvent::ExitToSearch, "mail"),
+            None
+        );
+    }
+}
@@ -1,9 +1,26 @@
 use async_trait::async_trait;
 
-use super::ExtensionManifest;
+use crate::{commands::CommandResult, state::Item};
+
+use super::{ExtensionManifest, ExtensionStatus, LogEntry};
 
 #[async_trait]
 pub trait Extension: Send + Sync {
     fn manifest(&self) -> &ExtensionManifest;
     async fn initialize(&mut self) -> anyhow::Result<()>;
+    /// Results for `query`. `keyword` is the keyword, as declared in the
+    /// manifest, that routed the query to this extension, in which case it
+    /// has been stripped from the query.
+    async fn query(&self, query: &str, keyword: Option<&str>) -> anyhow::Result<Vec<Item>>;
+    /// Runs the action the extension registered as `action_id`, see
+    /// [`crate::commands::CommandType::ExtensionCallback`].
+    async fn run_action(
+        &self,
+        action_id: &str,
+        payload: serde_json::Value,
+    ) -> anyhow::Result<CommandResult>;
+    /// Items the extension's background jobs contribute to the root list.
+    fn background_items(&self) -> Vec<Item>;
+    fn status(&self) -> ExtensionStatus;
+    fn recent_logs(&self) -> Vec<LogEntry>;
 }
\ No newline at end of file
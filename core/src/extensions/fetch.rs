@@ -0,0 +1,492 @@
+use std::{borrow::Cow, cell::RefCell, rc::Rc, time::Duration};
+
+use anyhow::Result;
+use deno_core::{
+    AsyncRefCell, CancelFuture, CancelHandle, OpState, RcRef, Resource, ResourceId, op2,
+};
+use deno_error::JsErrorBox;
+use reqwest::{Method, redirect};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+const MAX_REDIRECTS: usize = 10;
+
+/// Hosts an extension is allowed to reach, as declared by `allowed_hosts` in
+/// its manifest.
+///
+/// Entries are either an exact host (`api.github.com`), a wildcard covering a
+/// domain and all of its subdomains (`*.example.com`), or either of those with
+/// an explicit port (`127.0.0.1:8080`). Entries without a port only match the
+/// scheme's default port.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostAllowlist {
+    patterns: Vec<HostPattern>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HostPattern {
+    host: String,
+    wildcard: bool,
+    port: Option<u16>,
+}
+
+impl HostAllowlist {
+    pub fn new<S: AsRef<str>>(entries: &[S]) -> Result<Self> {
+        let patterns = entries
+            .iter()
+            .map(|entry| HostPattern::parse(entry.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { patterns })
+    }
+
+    pub fn allows(&self, url: &Url) -> bool {
+        if !matches!(url.scheme(), "http" | "https") {
+            return false;
+        }
+
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.to_ascii_lowercase();
+
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.matches(&host, url))
+    }
+}
+
+impl HostPattern {
+    fn parse(entry: &str) -> Result<Self> {
+        let entry = entry.trim();
+        let (wildcard, rest) = match entry.strip_prefix("*.") {
+            Some(rest) => (true, rest),
+            None => (false, entry),
+        };
+
+        // Let the URL parser deal with IPv6 brackets and port validation.
+        let parsed = Url::parse(&format!("http://{rest}"))
+            .map_err(|e| anyhow::anyhow!("Invalid allowed host '{}': {}", entry, e))?;
+        let host = parsed
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Invalid allowed host '{}'", entry))?;
+
+        if parsed.path() != "/" || parsed.query().is_some() || !parsed.username().is_empty() {
+            anyhow::bail!(
+                "Allowed host '{}' must not contain a path or credentials",
+                entry
+            );
+        }
+
+        // `http://` drops a port of 80 as the default, a scheme without a
+        // default port keeps every port that is written out.
+        let port = Url::parse(&format!("cognito://{rest}"))
+            .ok()
+            .and_then(|url| url.port());
+
+        Ok(Self {
+            host: host.to_ascii_lowercase(),
+            wildcard,
+            port,
+        })
+    }
+
+    /// Entries without a port only match URLs on their scheme's default
+    /// port. Entries with one match it whether the URL writes it out or
+    /// leaves it as the default (`api.github.com:443` and `https://api.github.com/`).
+    fn matches(&self, host: &str, url: &Url) -> bool {
+        let port_matches = match self.port {
+            Some(port) => url.port_or_known_default() == Some(port),
+            None => url.port().is_none(),
+        };
+        if !port_matches {
+            return false;
+        }
+
+        if self.wildcard {
+            host == self.host
+                || host
+                    .strip_suffix(&self.host)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        } else {
+            host == self.host
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchRequest {
+    pub url: String,
+    #[serde(default)]
+    pub method: Option<String>,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(skip)]
+    pub body: Option<Vec<u8>>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub max_response_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchResponseHead {
+    pub url: String,
+    pub status: u16,
+    pub status_text: String,
+    pub headers: Vec<(String, String)>,
+}
+
+/// HTTP client handed to a single extension. Every request, including each
+/// redirect hop, is checked against the extension's [`HostAllowlist`].
+#[derive(Debug, Clone)]
+pub struct FetchClient {
+    client: reqwest::Client,
+    allowlist: HostAllowlist,
+}
+
+impl FetchClient {
+    pub fn new(allowlist: HostAllowlist) -> Result<Self> {
+        let redirect_allowlist = allowlist.clone();
+        let client = reqwest::Client::builder()
+            .redirect(redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if redirect_allowlist.allows(attempt.url()) {
+                    attempt.follow()
+                } else {
+                    let message = format!("redirect to {} is not allowed", attempt.url());
+                    attempt.error(message)
+                }
+            }))
+            .build()?;
+
+        Ok(Self { client, allowlist })
+    }
+
+    pub async fn send(&self, request: FetchRequest) -> Result<FetchResponse> {
+        let url = Url::parse(&request.url)?;
+        if !self.allowlist.allows(&url) {
+            anyhow::bail!("Host is not in the extension's allowed hosts: {}", url);
+        }
+
+        let method = match request.method {
+            Some(method) => Method::from_bytes(method.to_ascii_uppercase().as_bytes())?,
+            None => Method::GET,
+        };
+        let timeout = request
+            .timeout_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_TIMEOUT);
+
+        let mut builder = self.client.request(method, url).timeout(timeout);
+        for (name, value) in request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+
+        let limit = request
+            .max_response_bytes
+            .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+        let response = builder.send().await?;
+        if response
+            .content_length()
+            .is_some_and(|length| length > limit as u64)
+        {
+            anyhow::bail!("Response body exceeds the limit of {} bytes", limit);
+        }
+
+        Ok(FetchResponse {
+            response,
+            remaining: limit,
+            limit,
+        })
+    }
+}
+
+/// A response whose body is read chunk by chunk, failing once more than the
+/// requested number of bytes has been received.
+#[derive(Debug)]
+pub struct FetchResponse {
+    response: reqwest::Response,
+    remaining: usize,
+    limit: usize,
+}
+
+impl FetchResponse {
+    pub fn head(&self) -> FetchResponseHead {
+        let headers = self
+            .response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.to_string(), value.to_string()))
+            })
+            .collect();
+
+        FetchResponseHead {
+            url: self.response.url().to_string(),
+            status: self.response.status().as_u16(),
+            status_text: self
+                .response
+                .status()
+                .canonical_reason()
+                .unwrap_or_default()
+                .to_string(),
+            headers,
+        }
+    }
+
+    /// Returns the next chunk of the body, or `None` once it has been fully
+    /// read.
+    pub async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        let Some(chunk) = self.response.chunk().await? else {
+            return Ok(None);
+        };
+
+        if chunk.len() > self.remaining {
+            anyhow::bail!("Response body exceeds the limit of {} bytes", self.limit);
+        }
+        self.remaining -= chunk.len();
+
+        Ok(Some(chunk.to_vec()))
+    }
+}
+
+/// Per-runtime network state. `client` is `None` when the extension did not
+/// request [`Permission::Network`](super::Permission::Network).
+#[derive(Clone)]
+pub(crate) struct NetworkAccess {
+    pub(crate) client: Option<FetchClient>,
+}
+
+struct FetchResponseResource {
+    response: AsyncRefCell<FetchResponse>,
+    cancel: CancelHandle,
+}
+
+impl Resource for FetchResponseResource {
+    fn name(&self) -> Cow<'_, str> {
+        "cognitoFetchResponse".into()
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FetchStarted {
+    rid: ResourceId,
+    #[serde(flatten)]
+    head: FetchResponseHead,
+}
+
+#[op2(async)]
+#[serde]
+pub(crate) async fn op_cognito_fetch(
+    state: Rc<RefCell<OpState>>,
+    #[serde] mut request: FetchRequest,
+    #[buffer(copy)] body: Vec<u8>,
+) -> Result<FetchStarted, JsErrorBox> {
+    let client = state
+        .borrow()
+        .borrow::<NetworkAccess>()
+        .client
+        .clone()
+        .ok_or_else(|| {
+            JsErrorBox::new(
+                "PermissionDenied",
+                "Extension does not have the Network permission",
+            )
+        })?;
+
+    if !body.is_empty() {
+        request.body = Some(body);
+    }
+
+    let response = client
+        .send(request)
+        .await
+        .map_err(|e| JsErrorBox::type_error(e.to_string()))?;
+    let head = response.head();
+    let rid = state
+        .borrow_mut()
+        .resource_table
+        .add(FetchResponseResource {
+            response: AsyncRefCell::new(response),
+            cancel: CancelHandle::new(),
+        });
+
+    Ok(FetchStarted { rid, head })
+}
+
+#[op2(async)]
+#[buffer]
+pub(crate) async fn op_cognito_fetch_read(
+    state: Rc<RefCell<OpState>>,
+    #[smi] rid: ResourceId,
+) -> Result<Vec<u8>, JsErrorBox> {
+    let resource = state
+        .borrow()
+        .resource_table
+        .get::<FetchResponseResource>(rid)
+        .map_err(JsErrorBox::from_err)?;
+
+    let cancel = RcRef::map(&resource, |r| &r.cancel);
+    let mut response = RcRef::map(&resource, |r| &r.response).borrow_mut().await;
+    let chunk = response
+        .next_chunk()
+        .or_cancel(cancel)
+        .await
+        .map_err(|_| JsErrorBox::generic("Response body was closed"))?
+        .map_err(|e| JsErrorBox::type_error(e.to_string()))?;
+
+    // An empty buffer tells the JS side that the body is exhausted.
+    Ok(chunk.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    async fn mock_server(body: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Bind mock server");
+        let port = listener.local_addr().expect("Mock server address").port();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buffer = [0; 4096];
+                let _ = socket.read(&mut buffer).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        port
+    }
+
+    fn request(url: String) -> FetchRequest {
+        FetchRequest {
+            url,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_allowlist_matches_hosts() {
+        let allowlist =
+            HostAllowlist::new(&["api.github.com", "*.example.com", "127.0.0.1:8080"]).unwrap();
+
+        let allowed = |url: &str| allowlist.allows(&Url::parse(url).unwrap());
+
+        assert!(allowed("https://api.github.com/repos"));
+        assert!(allowed("https://API.GITHUB.COM/"));
+        assert!(allowed("https://example.com/"));
+        assert!(allowed("https://a.b.example.com/"));
+        assert!(allowed("http://127.0.0.1:8080/health"));
+
+        assert!(!allowed("https://github.com/"));
+        assert!(!allowed("https://api.github.com:8443/"));
+        assert!(!allowed("https://notexample.com/"));
+        assert!(!allowed("http://127.0.0.1:9090/"));
+        assert!(!allowed("file:///etc/passwd"));
+    }
+
+    #[test]
+    fn test_allowlist_matches_default_ports() {
+        let allowlist = HostAllowlist::new(&["api.github.com:443", "example.com:80"]).unwrap();
+
+        let allowed = |url: &str| allowlist.allows(&Url::parse(url).unwrap());
+
+        assert!(allowed("https://api.github.com/"));
+        assert!(allowed("https://api.github.com:443/"));
+        assert!(allowed("http://example.com/"));
+
+        assert!(!allowed("http://api.github.com/"));
+        assert!(!allowed("https://example.com/"));
+    }
+
+    #[test]
+    fn test_allowlist_rejects_invalid_entries() {
+        assert!(HostAllowlist::new(&["example.com/path"]).is_err());
+        assert!(HostAllowlist::new(&["user@example.com"]).is_err());
+        assert!(HostAllowlist::new(&[""]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_allowed_host() {
+        let port = mock_server("hello").await;
+        let allowlist = HostAllowlist::new(&[format!("127.0.0.1:{port}")]).unwrap();
+        let client = FetchClient::new(allowlist).unwrap();
+
+        let mut response = client
+            .send(request(format!("http://127.0.0.1:{port}/")))
+            .await
+            .expect("Fetch from mock server");
+        assert_eq!(response.head().status, 200);
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.next_chunk().await.unwrap() {
+            body.extend(chunk);
+        }
+        assert_eq!(body, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rejects_unlisted_host() {
+        let port = mock_server("hello").await;
+        let client = FetchClient::new(HostAllowlist::new(&["example.com"]).unwrap()).unwrap();
+
+        let result = client
+            .send(request(format!("http://127.0.0.1:{port}/")))
+            .await;
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("not in the extension's allowed hosts")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_enforces_response_size_cap() {
+        let port = mock_server("this body is too long").await;
+        let allowlist = HostAllowlist::new(&[format!("127.0.0.1:{port}")]).unwrap();
+        let client = FetchClient::new(allowlist).unwrap();
+
+        let result = client
+            .send(FetchRequest {
+                max_response_bytes: Some(4),
+                ..request(format!("http://127.0.0.1:{port}/"))
+            })
+            .await;
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("exceeds the limit")
+        );
+    }
+}
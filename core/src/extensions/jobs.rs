@@ -0,0 +1,177 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use deno_core::{OpState, op2, v8};
+use deno_error::JsErrorBox;
+use serde::Deserialize;
+
+use crate::{config::ExtensionsConfig, state::Item};
+
+/// How the host runs the jobs extensions schedule through `cognito.schedule`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct JobSettings {
+    pub(crate) min_interval: Duration,
+    pub(crate) suspend_while_hidden: bool,
+}
+
+impl From<&ExtensionsConfig> for JobSettings {
+    fn from(config: &ExtensionsConfig) -> Self {
+        Self {
+            min_interval: config.min_job_interval(),
+            suspend_while_hidden: config.suspend_jobs_while_hidden,
+        }
+    }
+}
+
+/// Items returned by an extension's jobs, by job id. They are shown in the
+/// root list, i.e. while the query is empty.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BackgroundItems(Arc<Mutex<BTreeMap<String, Vec<Item>>>>);
+
+impl BackgroundItems {
+    pub(crate) fn set(&self, job_id: &str, items: Vec<Item>) {
+        if let Ok(mut jobs) = self.0.lock() {
+            jobs.insert(job_id.to_string(), items);
+        }
+    }
+
+    fn remove(&self, job_id: &str) {
+        if let Ok(mut jobs) = self.0.lock() {
+            jobs.remove(job_id);
+        }
+    }
+
+    pub(crate) fn items(&self) -> Vec<Item> {
+        match self.0.lock() {
+            Ok(jobs) => jobs.values().flatten().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JobOptions {
+    /// Milliseconds between two runs.
+    interval: u64,
+    /// Keep running while the launcher is hidden.
+    #[serde(default)]
+    run_while_hidden: bool,
+}
+
+struct Job {
+    id: String,
+    interval: Duration,
+    run_while_hidden: bool,
+    handler: v8::Global<v8::Function>,
+    next_run: Instant,
+}
+
+/// Jobs scheduled by the extension.
+#[derive(Default)]
+struct Jobs(Vec<Job>);
+
+impl Job {
+    fn suspended(&self, settings: &JobSettings, hidden: bool) -> bool {
+        hidden && settings.suspend_while_hidden && !self.run_while_hidden
+    }
+}
+
+/// When the next job that is not suspended is due.
+pub(crate) fn next_due(state: &OpState, hidden: bool) -> Option<Instant> {
+    let settings = state.borrow::<JobSettings>();
+    state
+        .try_borrow::<Jobs>()?
+        .0
+        .iter()
+        .filter(|job| !job.suspended(settings, hidden))
+        .map(|job| job.next_run)
+        .min()
+}
+
+/// The jobs due at `now`, which are rescheduled for their next run.
+pub(crate) fn take_due(
+    state: &mut OpState,
+    now: Instant,
+    hidden: bool,
+) -> Vec<(String, v8::Global<v8::Function>)> {
+    let settings = *state.borrow::<JobSettings>();
+    let Some(jobs) = state.try_borrow_mut::<Jobs>() else {
+        return Vec::new();
+    };
+
+    jobs.0
+        .iter_mut()
+        .filter(|job| job.next_run <= now && !job.suspended(&settings, hidden))
+        .map(|job| {
+            job.next_run = now + job.interval;
+            (job.id.clone(), job.handler.clone())
+        })
+        .collect()
+}
+
+#[op2]
+pub(crate) fn op_cognito_schedule(
+    state: &mut OpState,
+    #[string] id: String,
+    #[serde] options: JobOptions,
+    #[global] handler: v8::Global<v8::Function>,
+) -> Result<(), JsErrorBox> {
+    if id.is_empty() {
+        return Err(JsErrorBox::type_error("Job id must not be empty"));
+    }
+
+    let min_interval = state.borrow::<JobSettings>().min_interval;
+    if !state.has::<Jobs>() {
+        state.put(Jobs::default());
+    }
+
+    let jobs = state.borrow_mut::<Jobs>();
+    jobs.0.retain(|job| job.id != id);
+    // The first run happens right away so results show up without waiting a
+    // whole interval
+    jobs.0.push(Job {
+        id,
+        interval: Duration::from_millis(options.interval).max(min_interval),
+        run_while_hidden: options.run_while_hidden,
+        handler,
+        next_run: Instant::now(),
+    });
+
+    Ok(())
+}
+
+#[op2(fast)]
+pub(crate) fn op_cognito_unschedule(state: &mut OpState, #[string] id: &str) {
+    if let Some(jobs) = state.try_borrow_mut::<Jobs>() {
+        jobs.0.retain(|job| job.id != id);
+    }
+    state.borrow::<BackgroundItems>().remove(id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_items_are_ordered_by_job() {
+        let items = BackgroundItems::default();
+        let item = |title: &str| Item {
+            title: title.to_string(),
+            ..Default::default()
+        };
+
+        items.set("unread", vec![item("3 unread")]);
+        items.set("builds", vec![item("CI passing"), item("Deploy queued")]);
+        items.set("unread", vec![item("4 unread")]);
+
+        let titles: Vec<_> = items.items().into_iter().map(|item| item.title).collect();
+        assert_eq!(titles, ["CI passing", "Deploy queued", "4 unread"]);
+
+        items.remove("builds");
+        assert_eq!(items.items().len(), 1);
+    }
+}
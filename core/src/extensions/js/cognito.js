@@ -0,0 +1,272 @@
+// Cognito extension API. Evaluated in every extension runtime before the
+// extension's entry file and exposed as `globalThis.cognito`.
+import { core } from "ext:core/mod.js";
+
+const {
+  op_cognito_fetch,
+  op_cognito_fetch_read,
+  op_cognito_log,
+  op_cognito_publish,
+  op_cognito_register_action,
+  op_cognito_register_provider,
+  op_cognito_schedule,
+  op_cognito_subscribe,
+  op_cognito_unschedule,
+  op_cognito_unsubscribe,
+} = core.ops;
+
+// Writes to the host log, tagged with the extension's name.
+const log = {
+  error: (...args) => op_cognito_log("error", core.consoleStringify(...args)),
+  warn: (...args) => op_cognito_log("warn", core.consoleStringify(...args)),
+  info: (...args) => op_cognito_log("info", core.consoleStringify(...args)),
+  debug: (...args) => op_cognito_log("debug", core.consoleStringify(...args)),
+  trace: (...args) => op_cognito_log("trace", core.consoleStringify(...args)),
+};
+
+globalThis.console = {
+  ...globalThis.console,
+  log: log.info,
+  info: log.info,
+  warn: log.warn,
+  error: log.error,
+  debug: log.debug,
+  trace: log.trace,
+};
+
+class FetchResponse {
+  #rid;
+  #bodyUsed = false;
+
+  constructor({ rid, url, status, statusText, headers }) {
+    this.#rid = rid;
+    this.url = url;
+    this.status = status;
+    this.statusText = statusText;
+    this.headers = Object.fromEntries(headers);
+  }
+
+  get ok() {
+    return this.status >= 200 && this.status < 300;
+  }
+
+  get bodyUsed() {
+    return this.#bodyUsed;
+  }
+
+  // Streams the body as Uint8Array chunks.
+  async *chunks() {
+    if (this.#bodyUsed) {
+      throw new TypeError("Response body has already been consumed");
+    }
+    this.#bodyUsed = true;
+
+    try {
+      while (true) {
+        const chunk = await op_cognito_fetch_read(this.#rid);
+        if (chunk.length === 0) {
+          return;
+        }
+        yield chunk;
+      }
+    } finally {
+      core.tryClose(this.#rid);
+    }
+  }
+
+  async bytes() {
+    const chunks = [];
+    let length = 0;
+    for await (const chunk of this.chunks()) {
+      chunks.push(chunk);
+      length += chunk.length;
+    }
+
+    const bytes = new Uint8Array(length);
+    let offset = 0;
+    for (const chunk of chunks) {
+      bytes.set(chunk, offset);
+      offset += chunk.length;
+    }
+    return bytes;
+  }
+
+  async text() {
+    return core.decode(await this.bytes());
+  }
+
+  async json() {
+    return JSON.parse(await this.text());
+  }
+
+  cancel() {
+    this.#bodyUsed = true;
+    core.tryClose(this.#rid);
+  }
+}
+
+// A small subset of `fetch`. Only hosts listed in the manifest's
+// `allowed_hosts` can be reached, and only with the Network permission.
+//
+// Extra options: `timeout` (milliseconds) and `maxResponseBytes`.
+async function fetch(url, options = {}) {
+  const body = options.body == null
+    ? new Uint8Array()
+    : typeof options.body === "string"
+    ? core.encode(options.body)
+    : new Uint8Array(options.body);
+
+  const head = await op_cognito_fetch({
+    url: String(url),
+    method: options.method ?? null,
+    headers: Object.entries(options.headers ?? {}).map((
+      [name, value],
+    ) => [name, String(value)]),
+    timeoutMs: options.timeout ?? null,
+    maxResponseBytes: options.maxResponseBytes ?? null,
+  }, body);
+
+  return new FetchResponse(head);
+}
+
+// Registers the function answering search queries. It receives the query
+// string and `{ keyword }`, the manifest keyword that routed the query to this
+// extension (already stripped from the query) or null, and returns (or
+// resolves to) an array of items. Registering again
+// replaces the previous provider.
+function registerProvider(provider) {
+  if (typeof provider !== "function") {
+    throw new TypeError("Provider must be a function");
+  }
+  op_cognito_register_provider(provider);
+}
+
+// Registers the handler for an extension-defined action. It receives the
+// action's payload and may return (or resolve to) a command result such as
+// `{ ShowNotification: "Archived" }`; returning nothing counts as success.
+function registerAction(id, handler) {
+  if (typeof handler !== "function") {
+    throw new TypeError("Action handler must be a function");
+  }
+  op_cognito_register_action(String(id), handler);
+}
+
+// Builds an item action that runs the handler registered as `id` with
+// `payload` when it is chosen.
+//
+// Options: `description`, `icon` and `shortcut` (e.g. "ctrl+shift+c").
+function action(title, id, payload = null, options = {}) {
+  return {
+    title: String(title),
+    description: options.description ?? null,
+    icon: options.icon ?? null,
+    shortcut: options.shortcut ?? null,
+    command_type: { ExtensionCallback: { action_id: String(id), payload } },
+  };
+}
+
+// Builds a command result asking for input. Once submitted and valid, the
+// handler registered as `id` runs with `payload` plus the field values as
+// `payload.values`. Fields are `{ id, label, type, required, placeholder }`
+// with a `type` of "text", "textarea", "dropdown" (with `options` of
+// `{ value, title }`), "checkbox", "date" (YYYY-MM-DD) or "file" (with
+// `directory`), and an optional `default`.
+//
+// Options: `submitLabel`.
+function form(title, fields, id, payload = {}, options = {}) {
+  return {
+    ShowForm: {
+      title: String(title),
+      fields,
+      submit_label: options.submitLabel ?? "Submit",
+      submit: { ExtensionCallback: { action_id: String(id), payload } },
+    },
+  };
+}
+
+// Timers. Callbacks run on the extension's event loop, also between queries.
+function setTimeout(callback, delay = 0, ...args) {
+  return queueTimer(false, callback, delay, args);
+}
+
+function setInterval(callback, delay = 0, ...args) {
+  return queueTimer(true, callback, delay, args);
+}
+
+function clearTimeout(id) {
+  if (typeof id === "number") {
+    core.cancelTimer(id);
+  }
+}
+
+function queueTimer(repeat, callback, delay, args) {
+  if (typeof callback !== "function") {
+    throw new TypeError("Timer callback must be a function");
+  }
+  return core.queueUserTimer(
+    core.getTimerDepth() + 1,
+    repeat,
+    Math.max(0, Number(delay) || 0),
+    () => callback(...args),
+  );
+}
+
+// Runs `job` every `options.interval` milliseconds, also while nothing is
+// being searched. The interval is raised to the configured minimum, and the
+// job is paused while the launcher is hidden unless `runWhileHidden` is set.
+// The first run happens right away. When the job returns (or resolves to) an
+// array of items, they replace the items it previously contributed to the
+// root list. Scheduling an id again replaces the job.
+function schedule(id, options, job) {
+  if (typeof job !== "function") {
+    throw new TypeError("Job must be a function");
+  }
+  op_cognito_schedule(String(id), {
+    interval: Number(options.interval),
+    runWhileHidden: Boolean(options.runWhileHidden),
+  }, job);
+}
+
+// Stops a job and removes its items from the root list.
+function unschedule(id) {
+  op_cognito_unschedule(String(id));
+}
+
+// Application events. `on` subscribes to a type ("queryChanged",
+// "itemActivated", "applicationStarted", "applicationClosing",
+// "launcherShown", "launcherHidden"), to custom events published by another
+// extension ("<extension>:<name>" or "<extension>:*"), or to everything
+// ("*"). Handlers receive
+// `{ type, source, payload }` and may be async. `on` returns a function that
+// removes the handler.
+//
+// `emit` publishes a custom event, namespaced with this extension's name.
+const events = {
+  on(type, handler) {
+    if (typeof handler !== "function") {
+      throw new TypeError("Handler must be a function");
+    }
+    const id = op_cognito_subscribe(String(type), handler);
+    return () => op_cognito_unsubscribe(id);
+  },
+  emit(name, payload = null) {
+    op_cognito_publish(String(name), payload);
+  },
+};
+
+globalThis.cognito = {
+  action,
+  events,
+  fetch,
+  form,
+  log,
+  registerAction,
+  registerProvider,
+  schedule,
+  unschedule,
+};
+globalThis.fetch = fetch;
+globalThis.setTimeout = setTimeout;
+globalThis.setInterval = setInterval;
+globalThis.clearTimeout = clearTimeout;
+globalThis.clearInterval = clearTimeout;
@@ -1,15 +1,37 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use extension::Extension;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use anyhow::Result;
 
+use crate::{
+    AppContext,
+    commands::{Action, Argument, CommandType},
+    state::Item,
+};
+
+mod code_cache;
+mod console;
+mod events;
 mod extension;
+mod fetch;
+mod jobs;
+mod runtime;
+mod snapshot;
+mod transpile;
 mod wasm_extension;
 
+use jobs::JobSettings;
 use wasm_extension::WasmExtension;
 
+pub use console::LogEntry;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtensionManifest {
     pub name: String,
@@ -18,6 +40,125 @@ pub struct ExtensionManifest {
     pub description: Option<String>,
     pub entry_file: String,
     pub permissions: Vec<Permission>,
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    /// When the extension's runtime is started. Without triggers it starts
+    /// with the application.
+    #[serde(default)]
+    pub activation: Vec<ActivationTrigger>,
+    /// Keywords that route queries to this extension only, e.g. `g` for
+    /// "g rust async".
+    #[serde(default)]
+    pub keywords: Vec<KeywordSpec>,
+    /// Commands found by searching for their title, e.g. "Open inbox".
+    #[serde(default)]
+    pub commands: Vec<CommandSpec>,
+    /// Items offered when no provider has results for a query, e.g. "Search
+    /// the web for ...".
+    #[serde(default)]
+    pub fallbacks: Vec<FallbackSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeywordSpec {
+    pub keyword: String,
+    /// Shown next to the keyword when it is suggested.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Typed after the keyword. Their placeholders are shown in the search
+    /// bar until they are typed.
+    #[serde(default)]
+    pub arguments: Vec<Argument>,
+}
+
+/// A command offered by an extension. It is found by searching for it even
+/// before the extension runs, and choosing it runs the action `id` (see
+/// `cognito.registerAction`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandSpec {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+impl CommandSpec {
+    /// Result item for the command of the extension `extension_id`.
+    pub fn item(&self, extension_id: Uuid) -> Item {
+        Item {
+            title: self.title.clone(),
+            description: self.description.clone(),
+            icon: self.icon.clone(),
+            actions: vec![Action {
+                title: "Run".to_string(),
+                description: None,
+                icon: None,
+                command_type: CommandType::ExtensionCallback {
+                    extension_id,
+                    action_id: self.id.clone(),
+                    payload: serde_json::Value::Null,
+                },
+                shortcut: None,
+            }],
+            ..Default::default()
+        }
+    }
+}
+
+/// A fallback offered by an extension. Choosing it runs the action `id`
+/// (see `cognito.registerAction`) with `{ query }` as payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FallbackSpec {
+    pub id: String,
+    /// `{query}` is replaced with the query, e.g. `Search the web for "{query}"`.
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+impl ExtensionManifest {
+    pub fn activates_on_startup(&self) -> bool {
+        self.activation.is_empty() || self.activation.contains(&ActivationTrigger::Startup)
+    }
+
+    /// Whether a query routed to the extension by `keyword`, as declared in
+    /// its manifest, activates it.
+    pub fn activates_on_keyword(&self, keyword: &str) -> bool {
+        self.activation.iter().any(|trigger| {
+            matches!(trigger, ActivationTrigger::Keyword(declared) if declared == keyword)
+        })
+    }
+
+    pub fn activates_on_command(&self, action_id: &str) -> bool {
+        self.activation.iter().any(|trigger| {
+            matches!(trigger, ActivationTrigger::Command(command) if command == action_id)
+        })
+    }
+
+    pub fn declares_fallback(&self, action_id: &str) -> bool {
+        self.fallbacks
+            .iter()
+            .any(|fallback| fallback.id == action_id)
+    }
+}
+
+/// Listed in the manifest's `activation` array, e.g.
+/// `activation = ["Startup", { Keyword = "gh" }, { Command = "open-inbox" }]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActivationTrigger {
+    /// Start with the application.
+    Startup,
+    /// Start once a query is routed to the extension by this keyword, one of
+    /// its `keywords`.
+    Keyword(String),
+    /// Start when the command with this id, one of its `commands`, is run.
+    Command(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
@@ -28,8 +169,61 @@ pub enum Permission {
     Clipboard,
 }
 
+/// Limits applied to an extension's JavaScript runtime, set in the `[limits]`
+/// table of its manifest. Values above the host maximums are clamped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceLimits {
+    pub max_heap_mb: usize,
+    pub call_timeout_ms: u64,
+    pub startup_timeout_ms: u64,
+}
+
+impl ResourceLimits {
+    const MAX_HEAP_MB: usize = 512;
+    const MAX_CALL_TIMEOUT_MS: u64 = 30_000;
+    const MAX_STARTUP_TIMEOUT_MS: u64 = 60_000;
+
+    pub fn max_heap_bytes(&self) -> usize {
+        self.max_heap_mb.clamp(1, Self::MAX_HEAP_MB) * 1024 * 1024
+    }
+
+    pub fn call_timeout(&self) -> Duration {
+        Duration::from_millis(self.call_timeout_ms.clamp(1, Self::MAX_CALL_TIMEOUT_MS))
+    }
+
+    pub fn startup_timeout(&self) -> Duration {
+        Duration::from_millis(
+            self.startup_timeout_ms
+                .clamp(1, Self::MAX_STARTUP_TIMEOUT_MS),
+        )
+    }
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            max_heap_mb: 64,
+            call_timeout_ms: 2_000,
+            startup_timeout_ms: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtensionStatus {
+    /// Loaded but not started yet, see [`ExtensionManifest::activation`].
+    Inactive,
+    Running,
+    /// The runtime crashed and will be restarted on the next call once the
+    /// backoff has elapsed.
+    Restarting,
+    /// The runtime crashed too many times in a row and will not be restarted.
+    Faulted(String),
+}
+
 pub struct ExtensionManager {
-    extensions: HashMap<Uuid, Box<dyn Extension>>,
+    extensions: HashMap<Uuid, Arc<dyn Extension>>,
 }
 
 impl ExtensionManager {
@@ -39,21 +233,83 @@ impl ExtensionManager {
         })
     }
     
-    pub async fn load_extension(&mut self, path: PathBuf) -> anyhow::Result<Uuid> {
-        let manifest: ExtensionManifest = {
-            let content = std::fs::read_to_string(path.clone().join("manifest.toml"))?;
-            toml::from_str(&content)?
-        };
+    /// Reads the manifest in `path` and initializes the extension. This does
+    /// not need the manager, so callers can avoid holding its lock while the
+    /// extension starts up; register the result with [`Self::add_extension`].
+    pub async fn load_extension(
+        path: PathBuf,
+        context: &AppContext,
+    ) -> anyhow::Result<(Uuid, Box<dyn Extension>)> {
+        let manifest = Self::read_manifest(&path)?;
 
         let id = Uuid::new_v4();
         
         // Create and initialize the WASM extension
-        let mut wasm_extension = WasmExtension::new(id, manifest, path);
+        let mut wasm_extension = WasmExtension::new(
+            id,
+            manifest,
+            path,
+            context.event_bus.clone(),
+            JobSettings::from(&context.get_config().extensions),
+        );
         wasm_extension.initialize().await?;
         
-        self.extensions.insert(id, Box::new(wasm_extension));
-        
-        Ok(id)
+        Ok((id, Box::new(wasm_extension)))
+    }
+
+    /// Time it takes to start the extension in `path` and evaluate its entry
+    /// file, keeping the snapshot and code cache in `cache_dir`. Without a
+    /// cache directory nothing is cached, which is what a first start costs.
+    /// Only built for benchmarks, with the `bench` feature.
+    #[cfg(any(test, feature = "bench"))]
+    pub async fn measure_startup(path: PathBuf, cache_dir: Option<PathBuf>) -> Result<Duration> {
+        let manifest = Self::read_manifest(&path)?;
+        let mut extension = WasmExtension::new(
+            Uuid::new_v4(),
+            manifest,
+            path,
+            crate::events::EventBus::new(),
+            JobSettings::from(&crate::config::ExtensionsConfig::default()),
+        )
+        .with_cache_dir(cache_dir);
+
+        let started = std::time::Instant::now();
+        extension.initialize().await?;
+        Ok(started.elapsed())
+    }
+
+    /// The manifest of the extension in the directory `path`.
+    pub fn read_manifest(path: &Path) -> Result<ExtensionManifest> {
+        let content = std::fs::read_to_string(path.join("manifest.toml"))?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    pub fn add_extension(&mut self, id: Uuid, extension: Box<dyn Extension>) {
+        self.extensions.insert(id, Arc::from(extension));
+    }
+
+    /// Handles to every loaded extension, so they can be queried without
+    /// holding the manager's lock.
+    pub fn extensions(&self) -> Vec<Arc<dyn Extension>> {
+        self.extensions.values().cloned().collect()
+    }
+
+    pub fn ids(&self) -> Vec<Uuid> {
+        self.extensions.keys().copied().collect()
+    }
+
+    pub fn logs(&self) -> Vec<(String, Vec<LogEntry>)> {
+        self.extensions
+            .values()
+            .map(|extension| (extension.manifest().name.clone(), extension.recent_logs()))
+            .collect()
+    }
+
+    pub fn statuses(&self) -> Vec<(String, ExtensionStatus)> {
+        self.extensions
+            .values()
+            .map(|extension| (extension.manifest().name.clone(), extension.status()))
+            .collect()
     }
 
     pub fn unload_extension(&mut self, id: Uuid) -> anyhow::Result<()> {
@@ -63,6 +319,24 @@ impl ExtensionManager {
         }
     }
 
+    pub fn get(&self, id: Uuid) -> Option<Arc<dyn Extension>> {
+        self.extensions.get(&id).cloned()
+    }
+
+    /// Result items for the commands the extensions declare.
+    pub fn command_items(&self) -> Vec<Item> {
+        self.extensions
+            .iter()
+            .flat_map(|(id, extension)| {
+                extension
+                    .manifest()
+                    .commands
+                    .iter()
+                    .map(|spec| spec.item(*id))
+            })
+            .collect()
+    }
+
     pub fn get_manifest(&self, id: Uuid) -> Option<&ExtensionManifest> {
         if let Some(extension) = self.extensions.get(&id) {
             return Some(extension.manifest());
@@ -70,4 +344,44 @@ impl ExtensionManager {
 
         None
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_activation_triggers() {
+        let manifest: ExtensionManifest = toml::from_str(
+            r#"
+            name = "github"
+            version = "1.0.0"
+            entry_file = "main.ts"
+            permissions = ["Network"]
+            activation = [{ Keyword = "gh" }, { Command = "open-inbox" }]
+            keywords = [{ keyword = "gh", description = "GitHub" }]
+            commands = [{ id = "open-inbox", title = "Open inbox" }]
+            "#,
+        )
+        .expect("Parse manifest");
+
+        assert!(!manifest.activates_on_startup());
+        assert!(manifest.activates_on_keyword("gh"));
+        assert!(!manifest.activates_on_keyword("ghost"));
+        assert_eq!(manifest.keywords[0].description.as_deref(), Some("GitHub"));
+        assert!(manifest.activates_on_command("open-inbox"));
+        assert!(!manifest.activates_on_command("archive"));
+
+        let extension_id = Uuid::new_v4();
+        let item = manifest.commands[0].item(extension_id);
+        assert_eq!(item.title, "Open inbox");
+        assert_eq!(
+            item.actions[0].command_type,
+            CommandType::ExtensionCallback {
+                extension_id,
+                action_id: "open-inbox".to_string(),
+                payload: serde_json::Value::Null,
+            }
+        );
+    }
+}
@@ -0,0 +1,759 @@
+use std::{
+    collections::HashMap,
+    future::poll_fn,
+    path::PathBuf,
+    rc::Rc,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use deno_core::{
+    JsRuntime, ModuleSpecifier, OpState, PollEventLoopOptions, RuntimeOptions, error::CoreError,
+    op2, v8,
+};
+use log::Level;
+use tokio::sync::{broadcast::error::RecvError, mpsc, oneshot};
+
+use crate::{commands::CommandResult, events::AppEvent, state::Item};
+
+use super::{
+    ResourceLimits,
+    console::ExtensionLog,
+    events::{self, EventAccess, ExtensionEvent},
+    fetch::NetworkAccess,
+    jobs::{self, BackgroundItems, JobSettings},
+    snapshot,
+    wasm_extension::{ExtensionModuleLoader, WasmExtension},
+};
+
+/// Everything the runtime thread needs to build an extension's `JsRuntime`.
+pub(crate) struct RuntimeConfig {
+    pub(crate) name: String,
+    pub(crate) extension_path: PathBuf,
+    pub(crate) entry_file: String,
+    pub(crate) limits: ResourceLimits,
+    pub(crate) network: NetworkAccess,
+    pub(crate) log: ExtensionLog,
+    pub(crate) events: EventAccess,
+    pub(crate) jobs: JobSettings,
+    pub(crate) background_items: BackgroundItems,
+    /// Where the startup snapshot, code cache and transpiled modules are
+    /// kept. Nothing is cached without one.
+    pub(crate) cache_dir: Option<PathBuf>,
+}
+
+pub(crate) enum RuntimeError {
+    /// The call failed but the runtime is still usable, e.g. the provider
+    /// threw an exception.
+    Failed(anyhow::Error),
+    /// The runtime was terminated or its thread exited. It must be restarted
+    /// before it can be used again.
+    Crashed(anyhow::Error),
+}
+
+type Reply<T> = oneshot::Sender<Result<T, RuntimeError>>;
+
+enum RuntimeRequest {
+    Query {
+        query: String,
+        keyword: Option<String>,
+        reply: Reply<Vec<Item>>,
+    },
+    Action {
+        action_id: String,
+        payload: serde_json::Value,
+        reply: Reply<CommandResult>,
+    },
+}
+
+/// The search provider registered by the extension through
+/// `cognito.registerProvider`.
+struct SearchProvider(v8::Global<v8::Function>);
+
+#[op2]
+pub(crate) fn op_cognito_register_provider(
+    state: &mut OpState,
+    #[global] provider: v8::Global<v8::Function>,
+) {
+    state.put(SearchProvider(provider));
+}
+
+/// Handlers registered through `cognito.registerAction`, by action id.
+#[derive(Default)]
+struct ActionHandlers(HashMap<String, v8::Global<v8::Function>>);
+
+#[op2]
+pub(crate) fn op_cognito_register_action(
+    state: &mut OpState,
+    #[string] action_id: String,
+    #[global] handler: v8::Global<v8::Function>,
+) {
+    if !state.has::<ActionHandlers>() {
+        state.put(ActionHandlers::default());
+    }
+    state
+        .borrow_mut::<ActionHandlers>()
+        .0
+        .insert(action_id, handler);
+}
+
+/// Handle to the thread that owns an extension's `JsRuntime`. `JsRuntime` is
+/// not `Send`, so every call is sent to that thread over a channel. The thread
+/// serves calls one at a time and arms a [`Watchdog`] when it starts one, so
+/// only the call that is running counts against its time limit, not the
+/// calls queued behind it. Event handlers and jobs are guarded the same way.
+pub(crate) struct ExtensionRuntime {
+    requests: mpsc::UnboundedSender<RuntimeRequest>,
+    isolate: v8::IsolateHandle,
+    terminated: Arc<AtomicBool>,
+}
+
+impl ExtensionRuntime {
+    pub(crate) async fn spawn(config: RuntimeConfig) -> Result<Self> {
+        let limits = config.limits;
+        let (requests, receiver) = mpsc::unbounded_channel();
+        let (isolate_sender, isolate_receiver) = oneshot::channel();
+        let (started_sender, started_receiver) = oneshot::channel();
+        let terminated = Arc::new(AtomicBool::new(false));
+
+        let thread_terminated = terminated.clone();
+        thread::Builder::new()
+            .name(format!("extension-{}", config.name))
+            .spawn(move || {
+                run(
+                    config,
+                    receiver,
+                    isolate_sender,
+                    started_sender,
+                    thread_terminated,
+                )
+            })?;
+
+        let isolate = isolate_receiver
+            .await
+            .map_err(|_| anyhow::anyhow!("Extension runtime exited before starting"))??;
+
+        let runtime = Self {
+            requests,
+            isolate,
+            terminated,
+        };
+
+        match tokio::time::timeout(limits.startup_timeout(), started_receiver).await {
+            Ok(Ok(result)) => result.map(|_| runtime),
+            Ok(Err(_)) => Err(anyhow::anyhow!("Extension runtime exited during startup")),
+            Err(_) => {
+                runtime.terminate();
+                Err(anyhow::anyhow!(
+                    "Extension did not finish loading within {}ms",
+                    limits.startup_timeout().as_millis()
+                ))
+            }
+        }
+    }
+
+    pub(crate) async fn query(
+        &self,
+        query: String,
+        keyword: Option<String>,
+    ) -> Result<Vec<Item>, RuntimeError> {
+        self.request(|reply| RuntimeRequest::Query {
+            query,
+            keyword,
+            reply,
+        })
+        .await
+    }
+
+    pub(crate) async fn run_action(
+        &self,
+        action_id: String,
+        payload: serde_json::Value,
+    ) -> Result<CommandResult, RuntimeError> {
+        self.request(|reply| RuntimeRequest::Action {
+            action_id,
+            payload,
+            reply,
+        })
+        .await
+    }
+
+    async fn request<T>(
+        &self,
+        request: impl FnOnce(Reply<T>) -> RuntimeRequest,
+    ) -> Result<T, RuntimeError> {
+        let (reply, response) = oneshot::channel();
+        self.requests
+            .send(request(reply))
+            .map_err(|_| RuntimeError::Crashed(anyhow::anyhow!("Extension runtime has exited")))?;
+
+        response.await.unwrap_or_else(|_| {
+            Err(RuntimeError::Crashed(anyhow::anyhow!(
+                "Extension runtime exited while handling a call"
+            )))
+        })
+    }
+
+    /// Stops whatever JavaScript is running. The runtime thread notices the
+    /// flag and exits instead of serving further requests.
+    fn terminate(&self) {
+        self.terminated.store(true, Ordering::SeqCst);
+        self.isolate.terminate_execution();
+    }
+}
+
+impl Drop for ExtensionRuntime {
+    fn drop(&mut self) {
+        // Closing the channel ends the thread once it is idle, but a runaway
+        // call has to be interrupted first.
+        self.terminate();
+    }
+}
+
+fn run(
+    config: RuntimeConfig,
+    mut receiver: mpsc::UnboundedReceiver<RuntimeRequest>,
+    isolate_sender: oneshot::Sender<Result<v8::IsolateHandle>>,
+    started_sender: oneshot::Sender<Result<()>>,
+    terminated: Arc<AtomicBool>,
+) {
+    // Async ops (such as fetch) need a tokio reactor on this thread
+    let tokio_runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(tokio_runtime) => tokio_runtime,
+        Err(e) => {
+            let _ = isolate_sender.send(Err(e.into()));
+            return;
+        }
+    };
+
+    let mut runtime = create_runtime(&config);
+
+    // Terminate the isolate instead of letting V8 abort the whole process
+    // when the extension runs out of heap. The limit is raised so that there
+    // is room left to unwind.
+    let heap_handle = runtime.v8_isolate().thread_safe_handle();
+    let heap_terminated = terminated.clone();
+    runtime.add_near_heap_limit_callback(move |current_limit, _initial_limit| {
+        heap_terminated.store(true, Ordering::SeqCst);
+        heap_handle.terminate_execution();
+        current_limit * 2
+    });
+
+    if isolate_sender
+        .send(Ok(runtime.v8_isolate().thread_safe_handle()))
+        .is_err()
+    {
+        return;
+    }
+
+    // Subscribe before loading so events published during startup (such as
+    // `ApplicationStarted`) are delivered once the handlers are registered
+    let mut app_events = config.events.bus.subscribe();
+
+    let started = tokio_runtime.block_on(load_entry_file(&mut runtime, &config));
+    let failed_to_start = started.is_err();
+    if started_sender.send(started).is_err() || failed_to_start {
+        return;
+    }
+
+    let watchdog = Watchdog::spawn(
+        runtime.v8_isolate().thread_safe_handle(),
+        terminated.clone(),
+    );
+
+    // Whether the launcher is hidden, which pauses most background jobs
+    let mut hidden = false;
+    // Set once the event loop ran out of work, e.g. timers, so it is not
+    // polled again until JavaScript had a chance to queue more
+    let mut event_loop_idle = false;
+
+    tokio_runtime.block_on(async {
+        loop {
+            let next_job = jobs::next_due(&runtime.op_state().borrow(), hidden);
+            let wake = tokio::select! {
+                request = receiver.recv() => Wake::Request(request),
+                event = app_events.recv() => Wake::Event(event),
+                () = tokio::time::sleep_until(next_job.unwrap_or_else(Instant::now).into()),
+                    if next_job.is_some() => Wake::JobsDue,
+                result = poll_fn(|cx| {
+                    let _armed = watchdog.arm(config.limits.call_timeout());
+                    runtime.poll_event_loop(cx, PollEventLoopOptions::default())
+                }), if !event_loop_idle => Wake::EventLoop(result),
+            };
+
+            let polled_event_loop = matches!(wake, Wake::EventLoop(_));
+            match wake {
+                Wake::Request(Some(RuntimeRequest::Query {
+                    query,
+                    keyword,
+                    reply,
+                })) => {
+                    let _armed = watchdog.arm(config.limits.call_timeout());
+                    let result = call_provider(&mut runtime, query, keyword).await;
+                    let _ = reply.send(into_reply(result, &terminated));
+                }
+                Wake::Request(Some(RuntimeRequest::Action {
+                    action_id,
+                    payload,
+                    reply,
+                })) => {
+                    let _armed = watchdog.arm(config.limits.call_timeout());
+                    let result = call_action(&mut runtime, &action_id, payload).await;
+                    let _ = reply.send(into_reply(result, &terminated));
+                }
+                Wake::Request(None) => break,
+                Wake::Event(Ok(event)) => {
+                    match event {
+                        AppEvent::LauncherShown => hidden = false,
+                        AppEvent::LauncherHidden => hidden = true,
+                        _ => {}
+                    }
+                    dispatch_event(&mut runtime, &config, &watchdog, &event).await;
+                }
+                Wake::Event(Err(RecvError::Lagged(missed))) => config
+                    .log
+                    .push(Level::Warn, format!("Missed {missed} events while busy")),
+                // The bus only closes when the application shuts down
+                Wake::Event(Err(RecvError::Closed)) => break,
+                Wake::JobsDue => run_due_jobs(&mut runtime, &config, &watchdog, hidden).await,
+                Wake::EventLoop(Ok(())) => event_loop_idle = true,
+                Wake::EventLoop(Err(e)) => config
+                    .log
+                    .push(Level::Error, format!("Uncaught error: {e}")),
+            }
+
+            if terminated.load(Ordering::SeqCst) {
+                break;
+            }
+            event_loop_idle &= polled_event_loop;
+        }
+    });
+}
+
+/// What woke the runtime thread up.
+enum Wake {
+    Request(Option<RuntimeRequest>),
+    Event(Result<AppEvent, RecvError>),
+    JobsDue,
+    EventLoop(Result<(), CoreError>),
+}
+
+/// Failures are crashes when the isolate was terminated while handling the
+/// request.
+fn into_reply<T>(result: Result<T>, terminated: &AtomicBool) -> Result<T, RuntimeError> {
+    match result {
+        Err(e) if terminated.load(Ordering::SeqCst) => Err(RuntimeError::Crashed(e)),
+        result => result.map_err(RuntimeError::Failed),
+    }
+}
+
+/// Terminates the isolate when JavaScript started by the runtime thread runs
+/// past its deadline. The watchdog thread exits when this is dropped.
+struct Watchdog {
+    shared: Arc<WatchdogShared>,
+    terminated: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct WatchdogShared {
+    deadline: Mutex<Option<Instant>>,
+    changed: Condvar,
+    stopped: AtomicBool,
+}
+
+impl Watchdog {
+    fn spawn(isolate: v8::IsolateHandle, terminated: Arc<AtomicBool>) -> Self {
+        let shared = Arc::new(WatchdogShared::default());
+
+        let thread_shared = shared.clone();
+        let thread_terminated = terminated.clone();
+        thread::spawn(move || {
+            let Ok(mut deadline) = thread_shared.deadline.lock() else {
+                return;
+            };
+
+            while !thread_shared.stopped.load(Ordering::SeqCst) {
+                deadline = match *deadline {
+                    Some(at) if Instant::now() >= at => {
+                        thread_terminated.store(true, Ordering::SeqCst);
+                        isolate.terminate_execution();
+                        *deadline = None;
+                        continue;
+                    }
+                    Some(at) => match thread_shared
+                        .changed
+                        .wait_timeout(deadline, at.saturating_duration_since(Instant::now()))
+                    {
+                        Ok((deadline, _)) => deadline,
+                        Err(_) => return,
+                    },
+                    None => match thread_shared.changed.wait(deadline) {
+                        Ok(deadline) => deadline,
+                        Err(_) => return,
+                    },
+                };
+            }
+        });
+
+        Self { shared, terminated }
+    }
+
+    /// Whether the isolate was terminated, by this watchdog or because it ran
+    /// out of heap.
+    fn terminated(&self) -> bool {
+        self.terminated.load(Ordering::SeqCst)
+    }
+
+    /// Starts the clock. The isolate is terminated unless the returned guard
+    /// is dropped within `timeout`.
+    fn arm(&self, timeout: Duration) -> WatchdogGuard<'_> {
+        self.set_deadline(Some(Instant::now() + timeout));
+        WatchdogGuard(self)
+    }
+
+    fn set_deadline(&self, at: Option<Instant>) {
+        if let Ok(mut deadline) = self.shared.deadline.lock() {
+            *deadline = at;
+        }
+        self.shared.changed.notify_one();
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        self.set_deadline(None);
+    }
+}
+
+struct WatchdogGuard<'a>(&'a Watchdog);
+
+impl Drop for WatchdogGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set_deadline(None);
+    }
+}
+
+fn create_runtime(config: &RuntimeConfig) -> JsRuntime {
+    // Create the runtime synchronously
+    let module_loader = Rc::new(ExtensionModuleLoader::new(
+        config.extension_path.clone(),
+        config.cache_dir.as_deref(),
+    ));
+
+    // Create the deno extension
+    let deno_ext = WasmExtension::create_deno_extension(
+        config.network.clone(),
+        config.log.clone(),
+        config.events.clone(),
+        config.jobs,
+        config.background_items.clone(),
+    );
+
+    // Create runtime options
+    let options = RuntimeOptions {
+        module_loader: Some(module_loader),
+        extensions: vec![deno_ext],
+        startup_snapshot: config
+            .cache_dir
+            .as_deref()
+            .and_then(snapshot::host_snapshot),
+        create_params: Some(
+            v8::CreateParams::default().heap_limits(0, config.limits.max_heap_bytes()),
+        ),
+        ..Default::default()
+    };
+
+    // Create the JS runtime
+    JsRuntime::new(options)
+}
+
+async fn load_entry_file(runtime: &mut JsRuntime, config: &RuntimeConfig) -> Result<()> {
+    // Try to load and execute the entry file
+    let entry_path = config.extension_path.join(&config.entry_file);
+    if !entry_path.exists() {
+        return Ok(());
+    }
+
+    let module_specifier = ModuleSpecifier::from_file_path(&entry_path)
+        .map_err(|e| anyhow::anyhow!("Failed to create module specifier: {:?}", e))?;
+
+    // Load and execute the module
+    let mod_id = runtime
+        .load_main_es_module(&module_specifier)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to load module: {:?}", e))?;
+
+    // Only wait for the module itself: timers it starts keep the event loop
+    // busy for as long as the extension is loaded
+    let result = runtime.mod_evaluate(mod_id);
+    runtime
+        .with_event_loop_promise(Box::pin(result), PollEventLoopOptions::default())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to evaluate module: {:?}", e))
+}
+
+async fn call_provider(
+    runtime: &mut JsRuntime,
+    query: String,
+    keyword: Option<String>,
+) -> Result<Vec<Item>> {
+    let provider = runtime
+        .op_state()
+        .borrow()
+        .try_borrow::<SearchProvider>()
+        .map(|provider| provider.0.clone());
+
+    // Extensions without a provider simply don't contribute results
+    let Some(provider) = provider else {
+        return Ok(Vec::new());
+    };
+
+    let (query, context) = {
+        let scope = &mut runtime.handle_scope();
+        let query = v8::String::new(scope, &query)
+            .ok_or_else(|| anyhow::anyhow!("Query is too long to pass to the extension"))?;
+        let context = deno_core::serde_v8::to_v8(scope, serde_json::json!({ "keyword": keyword }))
+            .map_err(|e| anyhow::anyhow!("Failed to pass the query to the extension: {}", e))?;
+        (
+            v8::Global::new(scope, v8::Local::<v8::Value>::from(query)),
+            v8::Global::new(scope, context),
+        )
+    };
+
+    let call = runtime.call_with_args(&provider, &[query, context]);
+    let value = runtime
+        .with_event_loop_promise(Box::pin(call), PollEventLoopOptions::default())
+        .await
+        .map_err(|e| anyhow::anyhow!("Provider failed: {}", e))?;
+
+    let scope = &mut runtime.handle_scope();
+    let value = v8::Local::new(scope, value);
+    let items = deno_core::serde_v8::from_v8(scope, value)
+        .map_err(|e| anyhow::anyhow!("Provider returned invalid items: {}", e))?;
+
+    Ok(items)
+}
+
+async fn call_action(
+    runtime: &mut JsRuntime,
+    action_id: &str,
+    payload: serde_json::Value,
+) -> Result<CommandResult> {
+    let handler = runtime
+        .op_state()
+        .borrow()
+        .try_borrow::<ActionHandlers>()
+        .and_then(|handlers| handlers.0.get(action_id).cloned())
+        .ok_or_else(|| anyhow::anyhow!("Unknown action '{}'", action_id))?;
+
+    let payload = {
+        let scope = &mut runtime.handle_scope();
+        let payload = deno_core::serde_v8::to_v8(scope, payload)
+            .map_err(|e| anyhow::anyhow!("Failed to pass payload to the extension: {}", e))?;
+        v8::Global::new(scope, payload)
+    };
+
+    let call = runtime.call_with_args(&handler, &[payload]);
+    let value = runtime
+        .with_event_loop_promise(Box::pin(call), PollEventLoopOptions::default())
+        .await
+        .map_err(|e| anyhow::anyhow!("Action '{}' failed: {}", action_id, e))?;
+
+    let scope = &mut runtime.handle_scope();
+    let value = v8::Local::new(scope, value);
+    // Handlers that return nothing succeeded
+    if value.is_null_or_undefined() {
+        return Ok(CommandResult::Success);
+    }
+
+    deno_core::serde_v8::from_v8(scope, value)
+        .map_err(|e| anyhow::anyhow!("Action '{}' returned an invalid result: {}", action_id, e))
+}
+
+/// Runs the jobs that are due. Items returned by a job replace the ones it
+/// contributed to the root list before.
+async fn run_due_jobs(
+    runtime: &mut JsRuntime,
+    config: &RuntimeConfig,
+    watchdog: &Watchdog,
+    hidden: bool,
+) {
+    let due = jobs::take_due(&mut runtime.op_state().borrow_mut(), Instant::now(), hidden);
+    for (job_id, handler) in due {
+        let result = {
+            let _armed = watchdog.arm(config.limits.call_timeout());
+            call_job(runtime, &handler).await
+        };
+
+        match result {
+            Ok(Some(items)) => {
+                config.background_items.set(&job_id, items);
+                let _ = config
+                    .events
+                    .bus
+                    .publish(AppEvent::ExtensionItemsChanged(config.name.clone()));
+            }
+            Ok(None) => {}
+            Err(e) if watchdog.terminated() => {
+                config
+                    .log
+                    .push(Level::Error, format!("Job '{job_id}' was terminated: {e}"));
+                return;
+            }
+            Err(e) => config
+                .log
+                .push(Level::Warn, format!("Job '{job_id}' failed: {e}")),
+        }
+    }
+}
+
+async fn call_job(
+    runtime: &mut JsRuntime,
+    handler: &v8::Global<v8::Function>,
+) -> Result<Option<Vec<Item>>> {
+    let call = runtime.call_with_args(handler, &[]);
+    let value = runtime
+        .with_event_loop_promise(Box::pin(call), PollEventLoopOptions::default())
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let scope = &mut runtime.handle_scope();
+    let value = v8::Local::new(scope, value);
+    // Jobs that don't return items leave the root list alone
+    if !value.is_array() {
+        return Ok(None);
+    }
+
+    deno_core::serde_v8::from_v8(scope, value)
+        .map(Some)
+        .map_err(|e| anyhow::anyhow!("Job returned invalid items: {}", e))
+}
+
+/// Calls every handler the extension subscribed to `event` with. Failing
+/// handlers are logged and don't keep the others from running.
+async fn dispatch_event(
+    runtime: &mut JsRuntime,
+    config: &RuntimeConfig,
+    watchdog: &Watchdog,
+    event: &AppEvent,
+) {
+    let Some(event) = ExtensionEvent::from_app_event(event, &config.name) else {
+        return;
+    };
+
+    let handlers = events::handlers_for(&runtime.op_state().borrow(), &event.kind);
+    for handler in handlers {
+        let result = {
+            let _armed = watchdog.arm(config.limits.call_timeout());
+            call_handler(runtime, &handler, &event).await
+        };
+
+        if let Err(e) = result {
+            if watchdog.terminated() {
+                config.log.push(
+                    Level::Error,
+                    format!("Handler for '{}' was terminated: {e}", event.kind),
+                );
+                return;
+            }
+            config.log.push(
+                Level::Warn,
+                format!("Handler for '{}' failed: {e}", event.kind),
+            );
+        }
+    }
+}
+
+async fn call_handler(
+    runtime: &mut JsRuntime,
+    handler: &v8::Global<v8::Function>,
+    event: &ExtensionEvent,
+) -> Result<()> {
+    let event = {
+        let scope = &mut runtime.handle_scope();
+        let event = deno_core::serde_v8::to_v8(scope, event)
+            .map_err(|e| anyhow::anyhow!("Failed to pass event to the extension: {}", e))?;
+        v8::Global::new(scope, event)
+    };
+
+    let call = runtime.call_with_args(handler, &[event]);
+    runtime
+        .with_event_loop_promise(Box::pin(call), PollEventLoopOptions::default())
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventBus;
+
+    async fn spawn(script: &str, call_timeout_ms: u64) -> ExtensionRuntime {
+        let dir = std::env::temp_dir().join(format!("cognito-runtime-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.js"), script).unwrap();
+
+        ExtensionRuntime::spawn(RuntimeConfig {
+            name: "test".to_string(),
+            extension_path: dir,
+            entry_file: "main.js".to_string(),
+            limits: ResourceLimits {
+                call_timeout_ms,
+                ..Default::default()
+            },
+            network: NetworkAccess { client: None },
+            log: ExtensionLog::new("test"),
+            events: EventAccess {
+                name: "test".to_string(),
+                bus: EventBus::new(),
+            },
+            jobs: JobSettings {
+                min_interval: Duration::from_secs(1),
+                suspend_while_hidden: true,
+            },
+            background_items: BackgroundItems::default(),
+            cache_dir: None,
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_runaway_call_is_terminated() {
+        let runtime = spawn("cognito.registerProvider(() => { while (true) {} });", 200).await;
+
+        let result = runtime.query("foo".to_string(), None).await;
+        assert!(matches!(result, Err(RuntimeError::Crashed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_queued_calls_do_not_time_out() {
+        // Each call takes a third of the limit, so the last ones would time
+        // out if waiting in the queue counted against it
+        let runtime = spawn(
+            r#"cognito.registerProvider((query) => {
+                const end = Date.now() + 100;
+                while (Date.now() < end) {}
+                return [{ title: query }];
+            });"#,
+            300,
+        )
+        .await;
+
+        let queries = (0..5).map(|i| runtime.query(i.to_string(), None));
+        for result in futures::future::join_all(queries).await {
+            assert!(result.is_ok());
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+};
+
+use anyhow::Result;
+use deno_core::{Extension, JsRuntime, JsRuntimeForSnapshot, RuntimeOptions, v8};
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+
+use crate::{config::ExtensionsConfig, events::EventBus};
+
+use super::{
+    console::ExtensionLog,
+    events::EventAccess,
+    fetch::NetworkAccess,
+    jobs::{BackgroundItems, JobSettings},
+    wasm_extension::WasmExtension,
+};
+
+type Snapshots = HashMap<PathBuf, Option<&'static [u8]>>;
+
+/// Snapshots already loaded by this process, by cache directory. They are
+/// leaked because V8 needs them for as long as runtimes are created.
+static SNAPSHOTS: LazyLock<Mutex<Snapshots>> = LazyLock::new(Default::default);
+
+/// V8 startup snapshot with the Cognito prelude already evaluated, so new
+/// runtimes start from it instead of evaluating the prelude again. It is
+/// created on first use and stored in `cache_dir` for later starts. Returns
+/// `None` when the snapshot can't be created; runtimes then start without one.
+pub(crate) fn host_snapshot(cache_dir: &Path) -> Option<&'static [u8]> {
+    // Runtimes starting at the same time wait for the first one to build it
+    let mut snapshots = SNAPSHOTS.lock().ok()?;
+    *snapshots
+        .entry(cache_dir.to_path_buf())
+        .or_insert_with(|| match load_or_create(cache_dir) {
+            Ok(snapshot) => Some(Box::leak(snapshot)),
+            Err(e) => {
+                warn!("Failed to create the extension runtime snapshot: {e}");
+                None
+            }
+        })
+}
+
+fn load_or_create(cache_dir: &Path) -> Result<Box<[u8]>> {
+    let dir = cache_dir.join("snapshots");
+    let path = dir.join(format!("{}.bin", snapshot_key()));
+    if let Ok(snapshot) = std::fs::read(&path) {
+        return Ok(snapshot.into_boxed_slice());
+    }
+
+    debug!("Creating extension runtime snapshot {path:?}");
+    let snapshot = create()?;
+
+    std::fs::create_dir_all(&dir)?;
+    // Never leave a truncated snapshot behind, V8 can't recover from it
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, &snapshot)?;
+    std::fs::rename(partial, path)?;
+
+    Ok(snapshot)
+}
+
+fn create() -> Result<Box<[u8]>> {
+    // Creating a snapshot initializes V8 with deterministic but slower flags
+    // unless it was initialized before, and V8 can only be initialized once
+    // per process
+    JsRuntime::init_platform(None, false);
+
+    let runtime = JsRuntimeForSnapshot::try_new(RuntimeOptions {
+        extensions: vec![extension()],
+        ..Default::default()
+    })?;
+
+    Ok(runtime.snapshot())
+}
+
+/// The extension runtimes are created with, as far as the snapshot is
+/// concerned. The options only seed the op state, which is not part of it.
+fn extension() -> Extension {
+    WasmExtension::create_deno_extension(
+        NetworkAccess { client: None },
+        ExtensionLog::new("snapshot"),
+        EventAccess {
+            name: "snapshot".to_string(),
+            bus: EventBus::new(),
+        },
+        JobSettings::from(&ExtensionsConfig::default()),
+        BackgroundItems::default(),
+    )
+}
+
+/// Snapshots only work with the V8 build, prelude and ops they were created
+/// from. Ops are referenced by index, so adding or reordering them makes an
+/// older snapshot unusable even when the version did not change.
+fn snapshot_key() -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION"));
+    hasher.update([0]);
+    hasher.update(v8::V8::get_version());
+    hasher.update([0]);
+    hasher.update(include_str!("js/cognito.js"));
+
+    let extension = extension();
+    hasher.update([0]);
+    hasher.update(extension.name);
+    for op in extension.ops.iter() {
+        hasher.update([0]);
+        hasher.update(op.name);
+        hasher.update([u8::from(op.is_async), op.arg_count]);
+    }
+    for file in extension
+        .get_js_sources()
+        .iter()
+        .chain(extension.get_esm_sources())
+    {
+        hasher.update([0]);
+        hasher.update(file.specifier);
+    }
+    hasher.update([0]);
+    hasher.update(extension.esm_entry_point.unwrap_or_default());
+
+    format!("{:x}", hasher.finalize())
+}
@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use deno_ast::{
+    EmitOptions, MediaType, ParseParams, SourceMapOption, TranspileModuleOptions, TranspileOptions,
+};
+use deno_core::ModuleSpecifier;
+use sha2::{Digest, Sha256};
+
+/// Bump whenever the transpile options change so stale output is ignored.
+const CACHE_VERSION: &str = "1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TranspiledModule {
+    pub(crate) code: String,
+    pub(crate) source_map: Vec<u8>,
+}
+
+/// Whether a module has to be transpiled to JavaScript before V8 can run it.
+pub(crate) fn needs_transpile(path: &Path) -> bool {
+    matches!(
+        MediaType::from_path(path),
+        MediaType::TypeScript | MediaType::Mts | MediaType::Cts | MediaType::Tsx | MediaType::Jsx
+    )
+}
+
+/// On-disk cache of transpiled TypeScript, keyed by a hash of the module's
+/// specifier and source, so unchanged files are not parsed again on startup.
+pub(crate) struct TranspileCache {
+    dir: Option<PathBuf>,
+}
+
+impl TranspileCache {
+    pub(crate) fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir }
+    }
+
+    pub(crate) fn transpile(
+        &self,
+        specifier: &ModuleSpecifier,
+        code: String,
+    ) -> Result<TranspiledModule> {
+        let key = cache_key(specifier, &code);
+        if let Some(cached) = self.read(&key) {
+            return Ok(cached);
+        }
+
+        let transpiled = transpile(specifier, code)?;
+
+        // The cache only speeds up the next start, so failing to write it is
+        // not an error
+        let _ = self.write(&key, &transpiled);
+
+        Ok(transpiled)
+    }
+
+    fn read(&self, key: &str) -> Option<TranspiledModule> {
+        let dir = self.dir.as_ref()?;
+        let code = std::fs::read_to_string(dir.join(format!("{key}.js"))).ok()?;
+        let source_map = std::fs::read(dir.join(format!("{key}.js.map"))).ok()?;
+
+        Some(TranspiledModule { code, source_map })
+    }
+
+    fn write(&self, key: &str, transpiled: &TranspiledModule) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+
+        std::fs::create_dir_all(dir)?;
+        // Write the map first: a module is only read back when both exist
+        write_atomically(&dir.join(format!("{key}.js.map")), &transpiled.source_map)?;
+        write_atomically(&dir.join(format!("{key}.js")), transpiled.code.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// Writes to a temporary file first so a concurrent reader never sees
+/// partial data.
+fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, data)?;
+    std::fs::rename(partial, path)?;
+
+    Ok(())
+}
+
+fn cache_key(specifier: &ModuleSpecifier, code: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(CACHE_VERSION);
+    hasher.update([0]);
+    hasher.update(specifier.as_str());
+    hasher.update([0]);
+    hasher.update(code);
+
+    format!("{:x}", hasher.finalize())
+}
+
+fn transpile(specifier: &ModuleSpecifier, code: String) -> Result<TranspiledModule> {
+    let media_type = specifier
+        .to_file_path()
+        .map(|path| MediaType::from_path(&path))
+        .unwrap_or(MediaType::TypeScript);
+
+    let parsed = deno_ast::parse_module(ParseParams {
+        specifier: specifier.clone(),
+        text: code.into(),
+        media_type,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })?;
+
+    let emitted = parsed
+        .transpile(
+            &TranspileOptions {
+                imports_not_used_as_values: deno_ast::ImportsNotUsedAsValues::Remove,
+                use_decorators_proposal: true,
+                ..Default::default()
+            },
+            &TranspileModuleOptions { module_kind: None },
+            &EmitOptions {
+                source_map: SourceMapOption::Separate,
+                inline_sources: true,
+                ..Default::default()
+            },
+        )?
+        .into_source();
+
+    Ok(TranspiledModule {
+        code: emitted.text,
+        source_map: emitted
+            .source_map
+            .map(String::into_bytes)
+            .unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn specifier() -> ModuleSpecifier {
+        ModuleSpecifier::parse("file:///extensions/example/main.ts").unwrap()
+    }
+
+    #[test]
+    fn test_transpile_strips_types() {
+        let transpiled = transpile(
+            &specifier(),
+            "const answer: number = 42;\nexport function double(x: number): number { return x * 2; }\n"
+                .to_string(),
+        )
+        .expect("Transpile TypeScript");
+
+        assert!(!transpiled.code.contains(": number"));
+        assert!(transpiled.code.contains("export function double(x)"));
+        assert!(!transpiled.source_map.is_empty());
+    }
+
+    #[test]
+    fn test_transpile_cache_reuses_output() {
+        let dir = std::env::temp_dir().join(format!("cognito-transpile-{}", uuid::Uuid::new_v4()));
+        let cache = TranspileCache {
+            dir: Some(dir.clone()),
+        };
+        let code = "export const greeting: string = 'hi';".to_string();
+
+        let first = cache.transpile(&specifier(), code.clone()).unwrap();
+        let key = cache_key(&specifier(), &code);
+        assert!(dir.join(format!("{key}.js")).exists());
+        assert_eq!(cache.read(&key), Some(first));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_needs_transpile() {
+        assert!(needs_transpile(Path::new("main.ts")));
+        assert!(needs_transpile(Path::new("view.tsx")));
+        assert!(!needs_transpile(Path::new("main.js")));
+        assert!(!needs_transpile(Path::new("data.json")));
+    }
+}
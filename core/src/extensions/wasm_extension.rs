@@ -1,12 +1,46 @@
 use async_trait::async_trait;
-use deno_core::{Extension, JsRuntime, RuntimeOptions, ModuleLoader, ModuleSource, ModuleSourceCode, ResolutionKind, ModuleSpecifier, ModuleLoadResponse};
+use deno_core::{
+    Extension, ModuleLoadResponse, ModuleLoader, ModuleSource, ModuleSourceCode, ModuleSpecifier,
+    ResolutionKind,
+};
 use deno_error::JsErrorBox;
-use futures::executor::block_on;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::rc::Rc;
-use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use anyhow::Result;
+use log::Level;
 
-use super::{Extension as CognitoExtension, ExtensionManifest};
+use futures::future::BoxFuture;
+
+use crate::{
+    commands::{CommandResult, CommandType},
+    events::EventBus,
+    icons,
+    state::Item,
+};
+
+use super::{
+    Extension as CognitoExtension, ExtensionManifest, ExtensionStatus, LogEntry, Permission,
+    console::{self, ExtensionLog},
+    events::{self, EventAccess},
+    fetch::{self, FetchClient, HostAllowlist, NetworkAccess},
+    jobs::{self, BackgroundItems, JobSettings},
+    runtime::{self, ExtensionRuntime, RuntimeConfig, RuntimeError},
+    code_cache::CodeCache,
+    transpile::{self, TranspileCache},
+};
+
+/// Consecutive crashes after which an extension is marked as faulted and no
+/// longer restarted.
+const MAX_CONSECUTIVE_CRASHES: u32 = 5;
+const INITIAL_RESTART_DELAY: Duration = Duration::from_millis(500);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
 
 // Define a simple op for our extension
 #[deno_core::op2]
@@ -18,29 +52,254 @@ fn op_hello_world() -> String {
 // Create the extension
 deno_core::extension!(
     cognito_wasm_extension,
-    ops = [op_hello_world],
+    ops = [
+        op_hello_world,
+        fetch::op_cognito_fetch,
+        fetch::op_cognito_fetch_read,
+        runtime::op_cognito_register_provider,
+        runtime::op_cognito_register_action,
+        console::op_cognito_log,
+        events::op_cognito_subscribe,
+        events::op_cognito_unsubscribe,
+        events::op_cognito_publish,
+        jobs::op_cognito_schedule,
+        jobs::op_cognito_unschedule,
+    ],
+    esm_entry_point = "ext:cognito_wasm_extension/cognito.js",
+    esm = [dir "src/extensions/js", "cognito.js"],
+    options = {
+        network: NetworkAccess,
+        log: ExtensionLog,
+        events: EventAccess,
+        jobs: JobSettings,
+        background_items: BackgroundItems,
+    },
+    state = |state, options| {
+        state.put(options.network);
+        state.put(options.log);
+        state.put(options.events);
+        state.put(options.jobs);
+        state.put(options.background_items);
+    },
 );
 
 pub struct WasmExtension {
+    id: uuid::Uuid,
     manifest: ExtensionManifest,
     extension_path: PathBuf,
+    supervisor: tokio::sync::Mutex<Supervisor>,
+    status: Mutex<ExtensionStatus>,
+    log: ExtensionLog,
+    event_bus: EventBus,
+    jobs: JobSettings,
+    background_items: BackgroundItems,
+    cache_dir: Option<PathBuf>,
 }
 
-// Make WasmExtension Send + Sync
-unsafe impl Send for WasmExtension {}
-unsafe impl Sync for WasmExtension {}
+/// Tracks the extension's runtime across crashes and decides when it may be
+/// restarted.
+#[derive(Default)]
+struct Supervisor {
+    runtime: Option<ExtensionRuntime>,
+    consecutive_crashes: u32,
+    restart_at: Option<Instant>,
+}
 
 impl WasmExtension {
-    pub fn new(_id: uuid::Uuid, manifest: ExtensionManifest, extension_path: PathBuf) -> Self {
+    pub fn new(
+        id: uuid::Uuid,
+        manifest: ExtensionManifest,
+        extension_path: PathBuf,
+        event_bus: EventBus,
+        jobs: JobSettings,
+    ) -> Self {
         Self {
+            id,
+            log: ExtensionLog::new(&manifest.name),
             manifest,
             extension_path,
+            supervisor: Default::default(),
+            status: Mutex::new(ExtensionStatus::Running),
+            event_bus,
+            jobs,
+            background_items: Default::default(),
+            cache_dir: dirs::cache_dir().map(|dir| dir.join("cognito")),
         }
     }
 
-    fn create_deno_extension() -> Extension {
+    /// Keeps the startup snapshot and code caches in `cache_dir` instead of
+    /// the user's cache directory, or disables caching without one.
+    #[cfg(any(test, feature = "bench"))]
+    pub(crate) fn with_cache_dir(mut self, cache_dir: Option<PathBuf>) -> Self {
+        self.cache_dir = cache_dir;
+        self
+    }
+
+    fn network_access(manifest: &ExtensionManifest) -> Result<NetworkAccess> {
+        // Only extensions that asked for network access get an HTTP client
+        let client = if manifest.permissions.contains(&Permission::Network) {
+            let allowlist = HostAllowlist::new(&manifest.allowed_hosts)?;
+            Some(FetchClient::new(allowlist)?)
+        } else {
+            None
+        };
+
+        Ok(NetworkAccess { client })
+    }
+
+    pub(super) fn create_deno_extension(
+        network: NetworkAccess,
+        log: ExtensionLog,
+        events: EventAccess,
+        jobs: JobSettings,
+        background_items: BackgroundItems,
+    ) -> Extension {
         // Create a deno extension with our custom ops
-        cognito_wasm_extension::init()
+        cognito_wasm_extension::init(network, log, events, jobs, background_items)
+    }
+
+    fn runtime_config(&self) -> Result<RuntimeConfig> {
+        Ok(RuntimeConfig {
+            name: self.manifest.name.clone(),
+            extension_path: self.extension_path.clone(),
+            entry_file: self.manifest.entry_file.clone(),
+            limits: self.manifest.limits,
+            network: Self::network_access(&self.manifest)?,
+            log: self.log.clone(),
+            events: EventAccess {
+                name: self.manifest.name.clone(),
+                bus: self.event_bus.clone(),
+            },
+            jobs: self.jobs,
+            background_items: self.background_items.clone(),
+            cache_dir: self.cache_dir.clone(),
+        })
+    }
+
+    fn is_inactive(&self) -> bool {
+        self.status() == ExtensionStatus::Inactive
+    }
+
+    fn set_status(&self, status: ExtensionStatus) {
+        if let Ok(mut current) = self.status.lock() {
+            *current = status;
+        }
+    }
+
+    async fn start_runtime(&self, supervisor: &mut Supervisor) -> Result<()> {
+        if let ExtensionStatus::Faulted(reason) = self.status() {
+            anyhow::bail!("Extension '{}' is disabled: {}", self.manifest.name, reason);
+        }
+
+        if supervisor
+            .restart_at
+            .is_some_and(|restart_at| Instant::now() < restart_at)
+        {
+            anyhow::bail!(
+                "Extension '{}' is waiting to be restarted",
+                self.manifest.name
+            );
+        }
+
+        if self.is_inactive() {
+            self.log.push(Level::Info, "Activating".to_string());
+        }
+
+        match ExtensionRuntime::spawn(self.runtime_config()?).await {
+            Ok(runtime) => {
+                supervisor.runtime = Some(runtime);
+                supervisor.restart_at = None;
+                self.set_status(ExtensionStatus::Running);
+                Ok(())
+            }
+            Err(e) => {
+                self.record_crash(supervisor, &e);
+                Err(e)
+            }
+        }
+    }
+
+    fn record_crash(&self, supervisor: &mut Supervisor, error: &anyhow::Error) {
+        supervisor.runtime = None;
+        supervisor.consecutive_crashes += 1;
+
+        if supervisor.consecutive_crashes >= MAX_CONSECUTIVE_CRASHES {
+            self.log.push(
+                Level::Error,
+                format!("Runtime crashed, disabling the extension: {error}"),
+            );
+            self.set_status(ExtensionStatus::Faulted(error.to_string()));
+            return;
+        }
+
+        let delay = INITIAL_RESTART_DELAY
+            .saturating_mul(1u32 << (supervisor.consecutive_crashes - 1))
+            .min(MAX_RESTART_DELAY);
+        self.log.push(
+            Level::Error,
+            format!(
+                "Runtime crashed, restarting in {}ms: {error}",
+                delay.as_millis()
+            ),
+        );
+        supervisor.restart_at = Some(Instant::now() + delay);
+        self.set_status(ExtensionStatus::Restarting);
+    }
+
+    /// Sends a call to the runtime, starting it first if it is not active yet
+    /// or restarting it if it crashed before.
+    async fn call<T>(
+        &self,
+        call: impl for<'a> FnOnce(&'a ExtensionRuntime) -> BoxFuture<'a, Result<T, RuntimeError>>,
+    ) -> Result<T> {
+        let mut supervisor = self.supervisor.lock().await;
+        if supervisor.runtime.is_none() {
+            self.start_runtime(&mut supervisor).await?;
+        }
+
+        let Some(runtime) = supervisor.runtime.as_ref() else {
+            anyhow::bail!("Extension '{}' is not running", self.manifest.name);
+        };
+
+        match call(runtime).await {
+            Ok(value) => {
+                supervisor.consecutive_crashes = 0;
+                Ok(value)
+            }
+            Err(RuntimeError::Failed(e)) => {
+                self.log.push(Level::Warn, e.to_string());
+                Err(e)
+            }
+            Err(RuntimeError::Crashed(e)) => {
+                self.record_crash(&mut supervisor, &e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Points the callback actions of `items` at this extension, and makes
+    /// icon paths relative to the extension's directory absolute.
+    fn claim_items(&self, items: &mut [Item]) {
+        for item in items.iter_mut() {
+            self.claim_icon(&mut item.icon);
+            for action in &mut item.actions {
+                self.claim_icon(&mut action.icon);
+                if let CommandType::ExtensionCallback { extension_id, .. } =
+                    &mut action.command_type
+                {
+                    *extension_id = self.id;
+                }
+            }
+        }
+    }
+
+    fn claim_icon(&self, icon: &mut Option<String>) {
+        if let Some(absolute) = icon
+            .as_deref()
+            .and_then(|spec| icons::resolve_relative(spec, &self.extension_path))
+        {
+            *icon = Some(absolute);
+        }
     }
 }
 
@@ -51,59 +310,114 @@ impl CognitoExtension for WasmExtension {
     }
 
     async fn initialize(&mut self) -> Result<()> {
-        // Run the initialization synchronously in a blocking task
-        let extension_path = self.extension_path.clone();
-        let entry_file = self.manifest.entry_file.clone();
-        
-        tokio::task::spawn_blocking(move || -> Result<()> {
-            // Create the runtime synchronously
-            let module_loader = Rc::new(ExtensionModuleLoader {
-                extension_path: extension_path.clone(),
-            });
-
-            // Create the deno extension
-            let deno_ext = WasmExtension::create_deno_extension();
-
-            // Create runtime options
-            let options = RuntimeOptions {
-                module_loader: Some(module_loader),
-                extensions: vec![deno_ext],
-                ..Default::default()
-            };
+        // Extensions with other triggers start once they are needed
+        if !self.manifest.activates_on_startup() {
+            self.set_status(ExtensionStatus::Inactive);
+            return Ok(());
+        }
 
-            // Create the JS runtime
-            let mut runtime = JsRuntime::new(options);
-            
-            // Try to load and execute the entry file
-            let entry_path = extension_path.join(&entry_file);
-            if entry_path.exists() {
-                let module_specifier = ModuleSpecifier::from_file_path(&entry_path)
-                    .map_err(|e| anyhow::anyhow!("Failed to create module specifier: {:?}", e))?;
-                
-                // Load and execute the module
-                let mod_id = block_on(runtime.load_main_es_module(&module_specifier))
-                    .map_err(|e| anyhow::anyhow!("Failed to load module: {:?}", e))?;
-                
-                let result = runtime.mod_evaluate(mod_id);
-                block_on(runtime.run_event_loop(Default::default()))
-                    .map_err(|e| anyhow::anyhow!("Failed to run event loop: {:?}", e))?;
-                
-                // We need to handle the result properly
-                // In a synchronous context, we can block on the future
-                block_on(result)
-                    .map_err(|e| anyhow::anyhow!("Failed to evaluate module: {:?}", e))?;
-            }
-            
-            Ok(())
-        }).await??;
+        // The runtime lives on its own thread for as long as the extension
+        // is loaded
+        let runtime = ExtensionRuntime::spawn(self.runtime_config()?).await?;
+        self.supervisor.get_mut().runtime = Some(runtime);
 
         Ok(())
     }
+
+    async fn query(&self, query: &str, keyword: Option<&str>) -> Result<Vec<Item>> {
+        let activates = keyword.is_some_and(|keyword| self.manifest.activates_on_keyword(keyword));
+        if self.is_inactive() && !activates {
+            return Ok(Vec::new());
+        }
+
+        let (query, keyword) = (query.to_string(), keyword.map(str::to_string));
+        let mut items = self
+            .call(|runtime| Box::pin(runtime.query(query, keyword)))
+            .await?;
+        self.claim_items(&mut items);
+
+        Ok(items)
+    }
+
+    async fn run_action(
+        &self,
+        action_id: &str,
+        payload: serde_json::Value,
+    ) -> Result<CommandResult> {
+        // Fallbacks are offered before the extension runs, so choosing one
+        // starts it like a command trigger would
+        if self.is_inactive()
+            && !self.manifest.activates_on_command(action_id)
+            && !self.manifest.declares_fallback(action_id)
+        {
+            anyhow::bail!(
+                "Extension '{}' is not active and '{}' is not one of its commands",
+                self.manifest.name,
+                action_id
+            );
+        }
+
+        let mut result = self
+            .call(|runtime| Box::pin(runtime.run_action(action_id.to_string(), payload)))
+            .await?;
+        if let CommandResult::ShowResults(items) | CommandResult::PushList { items, .. } =
+            &mut result
+        {
+            self.claim_items(items);
+        }
+        if let CommandResult::ShowDetail(item) = &mut result {
+            self.claim_items(std::slice::from_mut(item));
+        }
+        if let CommandResult::ShowForm(form) = &mut result
+            && let CommandType::ExtensionCallback { extension_id, .. } = &mut form.submit
+        {
+            *extension_id = self.id;
+        }
+
+        Ok(result)
+    }
+
+    fn background_items(&self) -> Vec<Item> {
+        let mut items = self.background_items.items();
+        self.claim_items(&mut items);
+        items
+    }
+
+    fn status(&self) -> ExtensionStatus {
+        match self.status.lock() {
+            Ok(status) => status.clone(),
+            Err(_) => ExtensionStatus::Faulted("Status lock poisoned".to_string()),
+        }
+    }
+
+    fn recent_logs(&self) -> Vec<LogEntry> {
+        self.log.entries()
+    }
 }
 
 // Custom module loader for extensions
-struct ExtensionModuleLoader {
+pub(super) struct ExtensionModuleLoader {
     extension_path: PathBuf,
+    transpile_cache: Rc<TranspileCache>,
+    code_cache: Rc<CodeCache>,
+    // Source maps of transpiled modules, so stack traces point at the
+    // original TypeScript
+    source_maps: Rc<RefCell<HashMap<String, Vec<u8>>>>,
+}
+
+impl ExtensionModuleLoader {
+    /// Transpiled modules and V8 code cache are stored below `cache_dir`, or
+    /// not cached at all without one.
+    pub(super) fn new(extension_path: PathBuf, cache_dir: Option<&Path>) -> Self {
+        Self {
+            extension_path,
+            transpile_cache: Rc::new(TranspileCache::new(
+                cache_dir.map(|dir| dir.join("transpiled")),
+            )),
+            code_cache: Rc::new(CodeCache::new(cache_dir.map(|dir| dir.join("code_cache")))),
+            source_maps: Default::default(),
+        }
+    }
 }
 
 impl ModuleLoader for ExtensionModuleLoader {
@@ -139,6 +453,9 @@ impl ModuleLoader for ExtensionModuleLoader {
     ) -> ModuleLoadResponse {
         let module_specifier = module_specifier.clone();
         let extension_path = self.extension_path.clone();
+        let transpile_cache = self.transpile_cache.clone();
+        let source_maps = self.source_maps.clone();
+        let code_cache = self.code_cache.clone();
 
         ModuleLoadResponse::Async(Box::pin(async move {
             // Convert the module specifier back to a file path
@@ -161,12 +478,117 @@ impl ModuleLoader for ExtensionModuleLoader {
                 deno_core::ModuleType::JavaScript
             };
 
+            let code = if transpile::needs_transpile(&path) {
+                let transpiled =
+                    transpile_cache
+                        .transpile(&module_specifier, code)
+                        .map_err(|e| {
+                            JsErrorBox::generic(format!(
+                                "Failed to transpile {}: {}",
+                                module_specifier, e
+                            ))
+                        })?;
+                source_maps
+                    .borrow_mut()
+                    .insert(module_specifier.to_string(), transpiled.source_map);
+                transpiled.code
+            } else {
+                code
+            };
+
+            let code_cache = matches!(module_type, deno_core::ModuleType::JavaScript)
+                .then(|| code_cache.get(&module_specifier, &code));
+
             Ok(ModuleSource::new(
                 module_type,
                 ModuleSourceCode::String(code.into()),
                 &module_specifier,
-                None,
+                code_cache,
             ))
         }))
     }
-}
\ No newline at end of file
+
+    fn code_cache_ready(
+        &self,
+        _module_specifier: ModuleSpecifier,
+        hash: u64,
+        code_cache: &[u8],
+    ) -> Pin<Box<dyn Future<Output = ()>>> {
+        // The cache only speeds up the next start, so failing to write it is
+        // not an error
+        let _ = self.code_cache.set(hash, code_cache);
+        Box::pin(async {})
+    }
+
+    fn get_source_map(&self, file_name: &str) -> Option<Cow<'_, [u8]>> {
+        self.source_maps
+            .borrow()
+            .get(file_name)
+            .map(|source_map| Cow::Owned(source_map.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extension() -> WasmExtension {
+        let manifest: ExtensionManifest = toml::from_str(
+            r#"
+            name = "flaky"
+            version = "1.0.0"
+            entry_file = "main.js"
+            permissions = []
+            "#,
+        )
+        .expect("Parse manifest");
+        let jobs = JobSettings {
+            min_interval: Duration::from_secs(1),
+            suspend_while_hidden: true,
+        };
+        WasmExtension::new(
+            uuid::Uuid::new_v4(),
+            manifest,
+            PathBuf::new(),
+            EventBus::new(),
+            jobs,
+        )
+        .with_cache_dir(None)
+    }
+
+    #[tokio::test]
+    async fn test_restart_backoff() {
+        let extension = extension();
+        let mut supervisor = Supervisor::default();
+
+        for expected in [500, 1_000, 2_000, 4_000] {
+            let crashed_at = Instant::now();
+            extension.record_crash(&mut supervisor, &anyhow::anyhow!("crashed"));
+            assert_eq!(extension.status(), ExtensionStatus::Restarting);
+
+            let delay = supervisor.restart_at.unwrap() - crashed_at;
+            assert!(delay >= Duration::from_millis(expected));
+            assert!(delay < Duration::from_millis(expected + 100));
+        }
+
+        let error = extension.start_runtime(&mut supervisor).await.unwrap_err();
+        assert!(error.to_string().contains("waiting to be restarted"));
+    }
+
+    #[tokio::test]
+    async fn test_faulted_after_consecutive_crashes() {
+        let extension = extension();
+        let mut supervisor = Supervisor::default();
+
+        for _ in 0..MAX_CONSECUTIVE_CRASHES {
+            extension.record_crash(&mut supervisor, &anyhow::anyhow!("crashed"));
+        }
+        assert_eq!(
+            extension.status(),
+            ExtensionStatus::Faulted("crashed".to_string())
+        );
+
+        let error = extension.start_runtime(&mut supervisor).await.unwrap_err();
+        assert!(error.to_string().contains("is disabled"));
+    }
+}
@@ -0,0 +1,135 @@
+use uuid::Uuid;
+
+use crate::{
+    commands::{Action, CommandType},
+    config::FallbacksConfig,
+    extensions::FallbackSpec,
+    state::Item,
+};
+
+/// A fallback offered by a provider, see [`FallbackSpec`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fallback {
+    /// `<provider name>:<fallback id>`, the name used in `AppConfig`.
+    pub name: String,
+    pub provider: Uuid,
+    pub spec: FallbackSpec,
+}
+
+/// Fallbacks of the providers that opted in, offered when nothing else
+/// matches a query.
+#[derive(Debug, Default)]
+pub struct FallbackRegistry {
+    fallbacks: Vec<Fallback>,
+}
+
+impl FallbackRegistry {
+    pub fn register(&mut self, provider_name: &str, provider: Uuid, spec: FallbackSpec) {
+        self.fallbacks.push(Fallback {
+            name: format!("{provider_name}:{}", spec.id),
+            provider,
+            spec,
+        });
+    }
+
+    /// Drops every fallback offered by `provider`.
+    pub fn unregister(&mut self, provider: Uuid) {
+        self.fallbacks
+            .retain(|fallback| fallback.provider != provider);
+    }
+
+    /// The enabled fallbacks, in the order configured in `config`.
+    pub fn ordered(&self, config: &FallbacksConfig) -> Vec<&Fallback> {
+        let mut fallbacks: Vec<_> = self
+            .fallbacks
+            .iter()
+            .filter(|fallback| !config.disabled.contains(&fallback.name))
+            .collect();
+        // Stable, so unlisted fallbacks keep their registration order
+        fallbacks.sort_by_key(|fallback| {
+            config
+                .order
+                .iter()
+                .position(|name| *name == fallback.name)
+                .unwrap_or(usize::MAX)
+        });
+        fallbacks
+    }
+
+    /// Result items for `query` made from the enabled fallbacks.
+    pub fn items(&self, query: &str, config: &FallbacksConfig) -> Vec<Item> {
+        self.ordered(config)
+            .into_iter()
+            .map(|fallback| fallback.item(query))
+            .collect()
+    }
+}
+
+impl Fallback {
+    /// Result item for `query`. Choosing it runs the fallback's action in
+    /// the provider with `{ query }` as payload.
+    pub fn item(&self, query: &str) -> Item {
+        Item {
+            title: self.spec.title.replace("{query}", query),
+            description: self.spec.description.clone(),
+            icon: self.spec.icon.clone(),
+            actions: vec![Action {
+                title: "Run".to_string(),
+                description: None,
+                icon: None,
+                command_type: CommandType::ExtensionCallback {
+                    extension_id: self.provider,
+                    action_id: self.spec.id.clone(),
+                    payload: serde_json::json!({ "query": query }),
+                },
+                shortcut: None,
+            }],
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(id: &str, title: &str) -> FallbackSpec {
+        FallbackSpec {
+            id: id.to_string(),
+            title: title.to_string(),
+            description: None,
+            icon: None,
+        }
+    }
+
+    #[test]
+    fn test_fallbacks_follow_config_order() {
+        let web = Uuid::new_v4();
+        let shell = Uuid::new_v4();
+
+        let mut registry = FallbackRegistry::default();
+        registry.register("web", web, spec("search", "Search the web for \"{query}\""));
+        registry.register("files", Uuid::new_v4(), spec("search", "Search files"));
+        registry.register("shell", shell, spec("run", "Run \"{query}\""));
+
+        let config = FallbacksConfig {
+            order: vec!["shell:run".to_string()],
+            disabled: vec!["files:search".to_string()],
+        };
+        let items = registry.items("ls -la", &config);
+
+        let titles: Vec<_> = items.iter().map(|item| item.title.as_str()).collect();
+        assert_eq!(titles, ["Run \"ls -la\"", "Search the web for \"ls -la\""]);
+        assert_eq!(
+            items[0].actions[0].command_type,
+            CommandType::ExtensionCallback {
+                extension_id: shell,
+                action_id: "run".to_string(),
+                payload: serde_json::json!({ "query": "ls -la" }),
+            }
+        );
+
+        registry.unregister(shell);
+        assert_eq!(registry.items("ls", &config).len(), 1);
+    }
+}
@@ -0,0 +1,266 @@
+use std::{collections::HashMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::commands::{CommandType, with_payload_entry};
+
+/// Values of a form's fields, by field id. Checkboxes hold booleans, every
+/// other field a string.
+pub type FormValues = Map<String, Value>;
+
+/// Input asked for by an action before it can run, e.g. the title, labels
+/// and priority of a new issue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Form {
+    pub title: String,
+    pub fields: Vec<Field>,
+    #[serde(default = "default_submit_label")]
+    pub submit_label: String,
+    /// Receives the values once they are valid, see [`Form::submission`].
+    /// Only extension callbacks can take them, so only extensions ask for
+    /// input; forms with any other command are rejected.
+    pub submit: CommandType,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Field {
+    pub id: String,
+    pub label: String,
+    #[serde(flatten)]
+    pub kind: FieldKind,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FieldKind {
+    Text {
+        #[serde(default)]
+        default: String,
+    },
+    /// Text spanning several lines.
+    Textarea {
+        #[serde(default)]
+        default: String,
+    },
+    Dropdown {
+        options: Vec<DropdownOption>,
+        #[serde(default)]
+        default: Option<String>,
+    },
+    Checkbox {
+        #[serde(default)]
+        default: bool,
+    },
+    /// A date written as `YYYY-MM-DD`.
+    Date {
+        #[serde(default)]
+        default: Option<String>,
+    },
+    /// Path of a file, or of a directory when `directory` is set.
+    File {
+        #[serde(default)]
+        directory: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DropdownOption {
+    pub value: String,
+    pub title: String,
+}
+
+impl fmt::Display for DropdownOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.title)
+    }
+}
+
+fn default_submit_label() -> String {
+    "Submit".to_string()
+}
+
+impl Form {
+    /// The values the fields start with.
+    pub fn initial_values(&self) -> FormValues {
+        self.fields
+            .iter()
+            .map(|field| {
+                let value = match &field.kind {
+                    FieldKind::Text { default } | FieldKind::Textarea { default } => {
+                        Value::String(default.clone())
+                    }
+                    FieldKind::Dropdown { default, .. } | FieldKind::Date { default } => {
+                        Value::String(default.clone().unwrap_or_default())
+                    }
+                    FieldKind::Checkbox { default } => Value::Bool(*default),
+                    FieldKind::File { .. } => Value::String(String::new()),
+                };
+                (field.id.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Problems with `values`, by field id. Empty when they can be submitted.
+    pub fn validate(&self, values: &FormValues) -> HashMap<String, String> {
+        self.fields
+            .iter()
+            .filter_map(|field| {
+                let error = field.validate(values.get(&field.id).unwrap_or(&Value::Null))?;
+                Some((field.id.clone(), error))
+            })
+            .collect()
+    }
+
+    /// Whether the values can be sent to the submit command, i.e. it is an
+    /// extension callback.
+    pub fn is_submittable(&self) -> bool {
+        matches!(self.submit, CommandType::ExtensionCallback { .. })
+    }
+
+    /// The submit command with `values` added. Extension callbacks get them
+    /// as `values` in their payload, next to what the payload already held.
+    pub fn submission(&self, values: FormValues) -> anyhow::Result<CommandType> {
+        let CommandType::ExtensionCallback {
+            extension_id,
+            action_id,
+            payload,
+        } = &self.submit
+        else {
+            anyhow::bail!("Form values can't be sent to {:?}", self.submit);
+        };
+
+        Ok(CommandType::ExtensionCallback {
+            extension_id: *extension_id,
+            action_id: action_id.clone(),
+            payload: with_payload_entry(payload.clone(), "values", Value::Object(values)),
+        })
+    }
+}
+
+impl Field {
+    fn validate(&self, value: &Value) -> Option<String> {
+        let text = value.as_str().unwrap_or_default().trim();
+        let missing = match &self.kind {
+            FieldKind::Checkbox { .. } => value.as_bool() != Some(true),
+            _ => text.is_empty(),
+        };
+        if missing {
+            return self.required.then(|| format!("{} is required", self.label));
+        }
+
+        match &self.kind {
+            FieldKind::Dropdown { options, .. }
+                if !options.iter().any(|option| option.value == text) =>
+            {
+                Some(format!("\"{text}\" is not one of the options"))
+            }
+            FieldKind::Date { .. } if !is_date(text) => {
+                Some("Dates are written as YYYY-MM-DD".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether `text` is a valid date written as `YYYY-MM-DD`.
+fn is_date(text: &str) -> bool {
+    let parts: Vec<_> = text.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return false;
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (
+        year.parse::<u32>(),
+        month.parse::<u32>(),
+        day.parse::<u32>(),
+    ) else {
+        return false;
+    };
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue_form() -> Form {
+        serde_json::from_value(serde_json::json!({
+            "title": "Create issue",
+            "fields": [
+                { "id": "title", "label": "Title", "type": "text", "required": true },
+                { "id": "body", "label": "Description", "type": "textarea" },
+                {
+                    "id": "priority",
+                    "label": "Priority",
+                    "type": "dropdown",
+                    "options": [
+                        { "value": "low", "title": "Low" },
+                        { "value": "high", "title": "High" },
+                    ],
+                    "default": "low",
+                },
+                { "id": "due", "label": "Due", "type": "date" },
+                { "id": "urgent", "label": "Urgent", "type": "checkbox" },
+            ],
+            "submit": { "ExtensionCallback": { "action_id": "create", "payload": { "repo": 7 } } },
+        }))
+        .expect("Parse form")
+    }
+
+    #[test]
+    fn test_form_validation() {
+        let form = issue_form();
+        let mut values = form.initial_values();
+        assert_eq!(values["priority"], "low");
+        assert_eq!(values["urgent"], false);
+
+        values.insert("due".to_string(), "2025-02-29".into());
+        let errors = form.validate(&values);
+        assert_eq!(errors.len(), 2);
+        assert!(errors["title"].contains("required"));
+        assert!(errors.contains_key("due"));
+
+        values.insert("title".to_string(), "Crash on start".into());
+        values.insert("due".to_string(), "2024-02-29".into());
+        assert!(form.validate(&values).is_empty());
+    }
+
+    #[test]
+    fn test_form_submission() {
+        let form = issue_form();
+        let mut values = form.initial_values();
+        values.insert("title".to_string(), "Crash on start".into());
+
+        let CommandType::ExtensionCallback { payload, .. } = form.submission(values).unwrap()
+        else {
+            panic!("Not an extension callback");
+        };
+        assert_eq!(payload["repo"], 7);
+        assert_eq!(payload["values"]["title"], "Crash on start");
+
+        let form = Form {
+            submit: CommandType::SetQuery {
+                query: "issue".to_string(),
+            },
+            ..issue_form()
+        };
+        assert!(!form.is_submittable());
+        assert!(form.submission(form.initial_values()).is_err());
+    }
+}
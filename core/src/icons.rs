@@ -0,0 +1,444 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+use anyhow::Context;
+use log::debug;
+use sha2::{Digest, Sha256};
+
+use crate::{config::IconsConfig, state::Section};
+
+/// File extensions of the images icons can be.
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "svg", "jpg", "jpeg", "gif", "webp", "ico"];
+
+/// A resolved icon, always a file on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Icon {
+    Raster(PathBuf),
+    Svg(PathBuf),
+}
+
+impl Icon {
+    fn from_path(path: PathBuf) -> Self {
+        if has_extension(&path, "svg") {
+            Icon::Svg(path)
+        } else {
+            Icon::Raster(path)
+        }
+    }
+}
+
+/// Resolves the icons of items and actions. An icon is one of
+///
+/// - a freedesktop icon theme name, e.g. `"firefox"`,
+/// - an absolute path to an image, e.g. `"/usr/share/pixmaps/htop.png"`,
+/// - a data URI, e.g. `"data:image/svg+xml;base64,..."`,
+/// - a path relative to the extension that returned it, e.g.
+///   `"assets/logo.svg"`, made absolute with [`resolve_relative`] when the
+///   extension's items are received.
+///
+/// Large images are scaled down to thumbnails and data URIs are decoded into
+/// the cache directory, where they are kept for later starts. Results are
+/// remembered, including icons that can't be resolved, for which the UI shows
+/// a fallback icon.
+#[derive(Debug)]
+pub struct IconResolver {
+    config: IconsConfig,
+    cache_dir: Option<PathBuf>,
+    resolved: Mutex<HashMap<String, Option<Icon>>>,
+}
+
+impl IconResolver {
+    pub fn new(config: IconsConfig, cache_dir: Option<PathBuf>) -> Self {
+        Self {
+            config,
+            cache_dir,
+            resolved: Mutex::default(),
+        }
+    }
+
+    /// The icon `spec` was resolved to, without resolving it when it wasn't
+    /// resolved yet.
+    pub fn cached(&self, spec: &str) -> Option<Icon> {
+        self.resolved.lock().ok()?.get(spec).cloned().flatten()
+    }
+
+    /// Resolves `spec`. Does file system work the first time, so it should
+    /// not be called from the UI thread.
+    pub fn resolve(&self, spec: &str) -> Option<Icon> {
+        if let Some(resolved) = self.resolved.lock().ok()?.get(spec) {
+            return resolved.clone();
+        }
+
+        let icon = match self.lookup(spec) {
+            Ok(icon) => icon,
+            Err(e) => {
+                debug!("Failed to resolve icon {spec:?}: {e}");
+                None
+            }
+        };
+        if let Ok(mut resolved) = self.resolved.lock() {
+            resolved.insert(spec.to_string(), icon.clone());
+        }
+
+        icon
+    }
+
+    /// Resolves the icons of the items in `sections` and of their actions.
+    pub fn resolve_sections(&self, sections: &[Section]) {
+        for spec in icon_specs(sections) {
+            self.resolve(spec);
+        }
+    }
+
+    /// The icons of `sections` that are resolved already, by spec.
+    pub fn cached_for(&self, sections: &[Section]) -> HashMap<String, Icon> {
+        let Ok(resolved) = self.resolved.lock() else {
+            return HashMap::new();
+        };
+
+        icon_specs(sections)
+            .filter_map(|spec| Some((spec.clone(), resolved.get(spec)?.clone()?)))
+            .collect()
+    }
+
+    fn lookup(&self, spec: &str) -> anyhow::Result<Option<Icon>> {
+        if spec.starts_with("data:") {
+            return self.decode_data_uri(spec).map(Some);
+        }
+
+        let path = Path::new(spec);
+        if path.is_absolute() {
+            if !path.is_file() {
+                return Ok(None);
+            }
+            return self.thumbnail(path).map(Some);
+        }
+
+        Ok(self.find_in_themes(spec))
+    }
+
+    fn decode_data_uri(&self, spec: &str) -> anyhow::Result<Icon> {
+        let url = data_url::DataUrl::process(spec).map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        let (data, _) = url.decode_to_vec().map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        let extension = match url.mime_type().subtype.as_str() {
+            "svg+xml" => "svg",
+            "jpeg" => "jpg",
+            "x-icon" | "vnd.microsoft.icon" => "ico",
+            subtype => subtype,
+        };
+        anyhow::ensure!(
+            IMAGE_EXTENSIONS.contains(&extension),
+            "Unsupported image type {}",
+            url.mime_type()
+        );
+
+        let path = self
+            .cache_path("data", &hash(spec.as_bytes()), extension)
+            .context("No cache directory to decode the icon into")?;
+        if !path.exists() {
+            write_atomically(&path, &data)?;
+        }
+
+        Ok(Icon::from_path(path))
+    }
+
+    /// `path`, or a scaled down copy when it is much larger than icons are
+    /// drawn. Copies are keyed by the path and modification time of the
+    /// original, so they are made again when it changes.
+    fn thumbnail(&self, path: &Path) -> anyhow::Result<Icon> {
+        let size = u32::from(self.config.size);
+        if has_extension(path, "svg") {
+            return Ok(Icon::Svg(path.to_path_buf()));
+        }
+
+        let modified = std::fs::metadata(path)?
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let key = hash(format!("{}\0{}\0{size}", path.display(), modified.as_nanos()).as_bytes());
+        let Some(thumbnail) = self.cache_path("thumbnails", &key, "png") else {
+            return Ok(Icon::Raster(path.to_path_buf()));
+        };
+        if thumbnail.exists() {
+            return Ok(Icon::Raster(thumbnail));
+        }
+
+        // Scaling is only worth it when it saves a lot, e.g. for photos
+        let (width, height) = image::image_dimensions(path)?;
+        if width.max(height) <= size * 4 {
+            return Ok(Icon::Raster(path.to_path_buf()));
+        }
+
+        let image = image::open(path)?.thumbnail(size * 2, size * 2);
+        let mut png = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut png, image::ImageFormat::Png)?;
+        write_atomically(&thumbnail, png.get_ref())?;
+
+        Ok(Icon::Raster(thumbnail))
+    }
+
+    /// Looks `name` up in the configured icon theme, the themes it inherits
+    /// from, `hicolor` and finally the legacy pixmaps directories.
+    fn find_in_themes(&self, name: &str) -> Option<Icon> {
+        if name.contains('/') {
+            return None;
+        }
+
+        let base_dirs = icon_base_dirs();
+        let mut themes = Vec::new();
+        let mut pending: Vec<String> = self.config.theme.iter().cloned().collect();
+        while let Some(theme) = pending.pop() {
+            if themes.contains(&theme) {
+                continue;
+            }
+            // Inherited themes are searched in order, before their parents
+            let mut parents = inherited_themes(&base_dirs, &theme);
+            parents.reverse();
+            pending.extend(parents);
+            themes.push(theme);
+        }
+        if !themes.iter().any(|theme| theme == "hicolor") {
+            themes.push("hicolor".to_string());
+        }
+
+        themes
+            .iter()
+            .find_map(|theme| {
+                base_dirs
+                    .iter()
+                    .find_map(|dir| self.find_in_theme_dir(&dir.join(theme), name))
+            })
+            .or_else(|| {
+                pixmap_dirs().into_iter().find_map(|dir| {
+                    IMAGE_EXTENSIONS
+                        .iter()
+                        .map(|extension| dir.join(format!("{name}.{extension}")))
+                        .find(|path| path.is_file())
+                        .map(Icon::from_path)
+                })
+            })
+    }
+
+    /// The best match for `name` in a theme directory, which holds icons as
+    /// `<size>/<context>/<name>` or `<context>/<size>/<name>`. Scalable icons
+    /// win, then the closest size, preferring larger ones.
+    fn find_in_theme_dir(&self, theme_dir: &Path, name: &str) -> Option<Icon> {
+        let wanted = i64::from(self.config.size);
+        let mut best: Option<(i64, PathBuf)> = None;
+
+        for first in read_dirs(theme_dir) {
+            for second in read_dirs(&first) {
+                for extension in ["svg", "png"] {
+                    let path = second.join(format!("{name}.{extension}"));
+                    if !path.is_file() {
+                        continue;
+                    }
+
+                    let size = [&first, &second]
+                        .into_iter()
+                        .find_map(|dir| directory_size(dir));
+                    let penalty = match size {
+                        None => 0,
+                        Some(size) if size >= wanted => size - wanted,
+                        // Scaling up looks worse than scaling down
+                        Some(size) => (wanted - size) * 4,
+                    };
+                    if best.as_ref().is_none_or(|(best, _)| penalty < *best) {
+                        best = Some((penalty, path));
+                    }
+                }
+            }
+        }
+
+        best.map(|(_, path)| Icon::from_path(path))
+    }
+
+    fn cache_path(&self, kind: &str, key: &str, extension: &str) -> Option<PathBuf> {
+        self.cache_dir
+            .as_ref()
+            .map(|dir| dir.join(kind).join(format!("{key}.{extension}")))
+    }
+}
+
+/// The icons of the items in `sections` and of their actions.
+fn icon_specs(sections: &[Section]) -> impl Iterator<Item = &String> {
+    sections
+        .iter()
+        .flat_map(|section| &section.items)
+        .flat_map(|item| {
+            item.icon.iter().chain(
+                item.actions
+                    .iter()
+                    .filter_map(|action| action.icon.as_ref()),
+            )
+        })
+}
+
+/// `spec` relative to the directory of the extension that returned it, or
+/// `None` when it is not a relative image path. Paths leaving the extension's
+/// directory are rejected.
+pub(crate) fn resolve_relative(spec: &str, extension_dir: &Path) -> Option<String> {
+    let path = Path::new(spec);
+    let is_image = IMAGE_EXTENSIONS
+        .iter()
+        .any(|extension| has_extension(path, extension));
+    if spec.starts_with("data:") || path.is_absolute() || !is_image {
+        return None;
+    }
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    Some(extension_dir.join(path).to_string_lossy().into_owned())
+}
+
+/// Size of the icons in a theme subdirectory like `48x48`, `48x48@2` or
+/// `48`, or `None` for `scalable`.
+fn directory_size(dir: &Path) -> Option<i64> {
+    let name = dir.file_name()?.to_str()?;
+    let (size, scale) = name.split_once('@').unwrap_or((name, "1"));
+    let size = size.split_once('x').map_or(size, |(width, _)| width);
+
+    Some(size.parse::<i64>().ok()? * scale.parse::<i64>().ok()?)
+}
+
+fn inherited_themes(base_dirs: &[PathBuf], theme: &str) -> Vec<String> {
+    base_dirs
+        .iter()
+        .find_map(|dir| std::fs::read_to_string(dir.join(theme).join("index.theme")).ok())
+        .and_then(|index| {
+            index.lines().find_map(|line| {
+                let value = line
+                    .strip_prefix("Inherits")?
+                    .trim_start()
+                    .strip_prefix('=')?;
+                Some(
+                    value
+                        .split(',')
+                        .map(|theme| theme.trim().to_string())
+                        .collect(),
+                )
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Directories holding icon themes, most specific first.
+fn icon_base_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(data) = dirs::data_dir() {
+        dirs.push(data.join("icons"));
+    }
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join(".icons"));
+    }
+    dirs.extend(xdg_data_dirs().into_iter().map(|dir| dir.join("icons")));
+    dirs
+}
+
+fn pixmap_dirs() -> Vec<PathBuf> {
+    xdg_data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("pixmaps"))
+        .collect()
+}
+
+fn xdg_data_dirs() -> Vec<PathBuf> {
+    let dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    std::env::split_paths(&dirs).collect()
+}
+
+fn read_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|found| found.eq_ignore_ascii_case(extension))
+}
+
+fn hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn write_atomically(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, data)?;
+    std::fs::rename(partial, path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("cognito-{name}-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_resolve_theme_icons_and_data_uris() {
+        let dir = temp_dir("icons");
+        let theme = dir.join("hicolor");
+        for (size, extension) in [("16x16", "png"), ("48x48", "png"), ("scalable", "svg")] {
+            let apps = theme.join(size).join("apps");
+            std::fs::create_dir_all(&apps).unwrap();
+            std::fs::write(apps.join(format!("editor.{extension}")), b"icon").unwrap();
+        }
+        std::fs::create_dir_all(theme.join("64x64").join("apps")).unwrap();
+        std::fs::write(theme.join("64x64/apps/viewer.png"), b"icon").unwrap();
+        std::fs::write(theme.join("16x16/apps/viewer.png"), b"icon").unwrap();
+
+        let resolver = IconResolver::new(IconsConfig::default(), Some(dir.join("cache")));
+        assert_eq!(
+            resolver.find_in_theme_dir(&theme, "editor"),
+            Some(Icon::Svg(theme.join("scalable/apps/editor.svg")))
+        );
+        assert_eq!(
+            resolver.find_in_theme_dir(&theme, "viewer"),
+            Some(Icon::Raster(theme.join("64x64/apps/viewer.png")))
+        );
+
+        let Some(Icon::Svg(decoded)) = resolver.resolve("data:image/svg+xml,%3Csvg%2F%3E") else {
+            panic!("Data URI was not decoded");
+        };
+        assert_eq!(std::fs::read(decoded).unwrap(), b"<svg/>");
+        assert_eq!(resolver.resolve("/does/not/exist.png"), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_relative() {
+        let dir = Path::new("/extensions/github");
+
+        assert_eq!(
+            resolve_relative("assets/logo.svg", dir).as_deref(),
+            Some("/extensions/github/assets/logo.svg")
+        );
+        assert_eq!(resolve_relative("../other/logo.svg", dir), None);
+        assert_eq!(resolve_relative("/usr/share/pixmaps/htop.png", dir), None);
+        assert_eq!(resolve_relative("firefox", dir), None);
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::{
+    commands::{Action, Argument, CommandType},
+    state::Item,
+};
+
+/// A keyword claimed by a provider. Queries starting with it are routed to
+/// that provider only, with the keyword stripped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyword {
+    /// What the user types, after applying the remapping from `AppConfig`.
+    pub keyword: String,
+    /// The keyword as declared by the provider, which is what the provider
+    /// is told it was queried with.
+    pub declared: String,
+    pub provider: Uuid,
+    pub description: Option<String>,
+    /// Typed after the keyword, e.g. the text of "translate <text>".
+    pub arguments: Vec<Argument>,
+}
+
+/// A query routed to a single provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub provider: Uuid,
+    /// The keyword as declared by the provider.
+    pub keyword: String,
+    /// The query without the keyword.
+    pub query: String,
+}
+
+/// Keywords claimed by providers. Extensions claim theirs in the `keywords`
+/// array of their manifest; built-in providers register with fixed ids.
+#[derive(Debug, Default)]
+pub struct KeywordRegistry {
+    keywords: Vec<Keyword>,
+}
+
+impl KeywordRegistry {
+    /// Claims `declared` for `provider`, as `remap` maps it (declared keyword
+    /// to the keyword the user wants to type; empty to disable it). Returns
+    /// an error when another provider already claimed the keyword.
+    pub fn register(
+        &mut self,
+        declared: &str,
+        provider: Uuid,
+        description: Option<String>,
+        arguments: Vec<Argument>,
+        remap: &HashMap<String, String>,
+    ) -> anyhow::Result<()> {
+        let keyword = remap
+            .get(declared)
+            .map(String::as_str)
+            .unwrap_or(declared)
+            .trim();
+        if keyword.is_empty() {
+            return Ok(());
+        }
+        if keyword.contains(char::is_whitespace) {
+            anyhow::bail!("Keyword '{keyword}' must not contain whitespace");
+        }
+
+        if let Some(existing) = self
+            .keywords
+            .iter()
+            .find(|existing| existing.keyword == keyword)
+        {
+            anyhow::bail!(
+                "Keyword '{keyword}' is already claimed by provider {}",
+                existing.provider
+            );
+        }
+
+        self.keywords.push(Keyword {
+            keyword: keyword.to_string(),
+            declared: declared.to_string(),
+            provider,
+            description,
+            arguments,
+        });
+
+        Ok(())
+    }
+
+    /// Drops every keyword claimed by `provider`.
+    pub fn unregister(&mut self, provider: Uuid) {
+        self.keywords.retain(|keyword| keyword.provider != provider);
+    }
+
+    /// The provider `query` is meant for, if it starts with a keyword. Word
+    /// keywords must be followed by whitespace or end the query ("g rust"),
+    /// while symbol keywords may be followed by anything ("=2+2"). The longest
+    /// matching keyword wins.
+    pub fn route(&self, query: &str) -> Option<Route> {
+        let query = query.trim_start();
+
+        self.keywords
+            .iter()
+            .filter_map(|keyword| {
+                let rest = query.strip_prefix(keyword.keyword.as_str())?;
+                let is_symbol = !keyword.keyword.chars().any(char::is_alphanumeric);
+                (is_symbol || rest.is_empty() || rest.starts_with(char::is_whitespace))
+                    .then_some((keyword, rest))
+            })
+            .max_by_key(|(keyword, _)| keyword.keyword.len())
+            .map(|(keyword, rest)| Route {
+                provider: keyword.provider,
+                keyword: keyword.declared.clone(),
+                query: rest.trim_start().to_string(),
+            })
+    }
+
+    /// Keywords to suggest for `query`: all of them when it is empty, and the
+    /// ones it is the beginning of while the first word is being typed.
+    pub fn suggestions(&self, query: &str) -> Vec<&Keyword> {
+        let query = query.trim_start();
+        if query.contains(char::is_whitespace) {
+            return Vec::new();
+        }
+
+        let mut suggestions: Vec<_> = self
+            .keywords
+            .iter()
+            .filter(|keyword| keyword.keyword.starts_with(query))
+            .collect();
+        suggestions.sort_by(|a, b| a.keyword.cmp(&b.keyword));
+        suggestions
+    }
+
+    /// The arguments of the keyword `query` starts with that are still to
+    /// be typed, to show their placeholders.
+    pub fn missing_arguments(&self, query: &str) -> Vec<&Argument> {
+        let Some(route) = self.route(query) else {
+            return Vec::new();
+        };
+        let Some(keyword) = self.keywords.iter().find(|keyword| {
+            keyword.provider == route.provider && keyword.declared == route.keyword
+        }) else {
+            return Vec::new();
+        };
+
+        let typed = route.query.split_whitespace().count();
+        keyword.arguments.iter().skip(typed).collect()
+    }
+}
+
+impl Keyword {
+    /// Result item for a keyword suggestion. Choosing it puts the keyword in
+    /// the search bar.
+    pub fn suggestion(&self) -> Item {
+        Item {
+            title: self.keyword.clone(),
+            description: self.description.clone(),
+            icon: None,
+            actions: vec![Action {
+                title: "Use keyword".to_string(),
+                description: None,
+                icon: None,
+                command_type: CommandType::SetQuery {
+                    query: format!("{} ", self.keyword),
+                },
+                shortcut: None,
+            }],
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> (KeywordRegistry, Uuid, Uuid) {
+        let web = Uuid::new_v4();
+        let calculator = Uuid::new_v4();
+        let remap = HashMap::from([("search".to_string(), "g".to_string())]);
+
+        let mut registry = KeywordRegistry::default();
+        let query = Argument {
+            name: "query".to_string(),
+            placeholder: None,
+            required: true,
+        };
+        registry
+            .register("search", web, None, vec![query], &remap)
+            .unwrap();
+        registry
+            .register("=", calculator, None, Vec::new(), &remap)
+            .unwrap();
+
+        (registry, web, calculator)
+    }
+
+    #[test]
+    fn test_route_strips_keyword() {
+        let (registry, web, calculator) = registry();
+
+        assert_eq!(
+            registry.route("g rust async"),
+            Some(Route {
+                provider: web,
+                keyword: "search".to_string(),
+                query: "rust async".to_string(),
+            })
+        );
+        assert_eq!(registry.route("= 2+2").unwrap().provider, calculator);
+        assert_eq!(registry.route("=2+2").unwrap().query, "2+2");
+        assert_eq!(registry.route("gimp"), None);
+        // The declared keyword only works under its new name
+        assert_eq!(registry.route("search rust"), None);
+    }
+
+    #[test]
+    fn test_suggestions_and_conflicts() {
+        let (mut registry, web, _) = registry();
+
+        assert_eq!(registry.suggestions("").len(), 2);
+        assert_eq!(registry.suggestions("g")[0].provider, web);
+        assert!(registry.suggestions("g rust").is_empty());
+
+        assert_eq!(registry.missing_arguments("g ")[0].name, "query");
+        assert!(registry.missing_arguments("g rust").is_empty());
+
+        assert!(
+            registry
+                .register("g", Uuid::new_v4(), None, Vec::new(), &HashMap::new())
+                .is_err()
+        );
+        registry.unregister(web);
+        assert_eq!(registry.route("g rust"), None);
+    }
+}
@@ -1,13 +1,24 @@
+use commands::{CommandResult, CommandType};
 use context::AppContext;
 use events::AppEvent;
-use std::path::PathBuf;
+use extensions::{ExtensionManager, ExtensionManifest, ExtensionStatus, LogEntry};
+use log::{error, info, warn};
+use state::{AppMode, Item, Section};
+use uuid::Uuid;
 
-mod commands;
-mod config;
+pub mod commands;
+pub mod config;
 mod context;
+pub mod daemon;
 pub mod events;
 pub mod extensions;
+mod fallbacks;
+pub mod forms;
+pub mod icons;
+mod keywords;
+pub mod logging;
 pub mod state;
+pub mod theme;
 
 #[derive(Clone)]
 pub struct Core {
@@ -24,12 +35,22 @@ impl Core {
     }
 
     fn register_core_commands(context: &AppContext) -> anyhow::Result<()> {
-        let _registry = context
+        let mut registry = context
             .command_registry
             .write()
             .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on command registry"))?;
 
-        // TODO: Register handlers
+        registry.register_handler(
+            "extension_callback".to_string(),
+            Box::new(commands::ExtensionCallbackHandler),
+        );
+        registry.register_handler("set_query".to_string(), Box::new(commands::SetQueryHandler));
+        registry.register_handler(
+            "extension_logs".to_string(),
+            Box::new(commands::ExtensionLogsHandler),
+        );
+
+        // TODO: Register the remaining handlers
 
         Ok(())
     }
@@ -47,13 +68,29 @@ impl Core {
         Ok(())
     }
 
-    async fn load_extensions(&mut self) -> anyhow::Result<()> {
-        // For now, we'll use a default extensions directory
-        // In the future, this should be configurable
-        let extensions_dir = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("cognito")
-            .join("extensions");
+    /// Unloads every extension and loads the ones installed now, e.g. after
+    /// extensions were installed or removed.
+    pub async fn reload_extensions(&self) -> anyhow::Result<()> {
+        {
+            let mut manager = self.context.extension_manager.write().map_err(|_| {
+                anyhow::anyhow!("Failed to acquire write lock on extension manager")
+            })?;
+            for id in manager.ids() {
+                manager.unload_extension(id)?;
+                if let Ok(mut keywords) = self.context.keywords.write() {
+                    keywords.unregister(id);
+                }
+                if let Ok(mut fallbacks) = self.context.fallbacks.write() {
+                    fallbacks.unregister(id);
+                }
+            }
+        }
+
+        self.load_extensions().await
+    }
+
+    async fn load_extensions(&self) -> anyhow::Result<()> {
+        let extensions_dir = config::extensions_dir()?;
 
         // Create the extensions directory if it doesn't exist
         if !extensions_dir.exists() {
@@ -62,9 +99,6 @@ impl Core {
 
         // Load all extensions in the directory
         if extensions_dir.exists() {
-            let mut extension_manager = self.context.extension_manager.write()
-                .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on extension manager"))?;
-
             // Iterate through directories in the extensions directory
             for entry in std::fs::read_dir(&extensions_dir)? {
                 let entry = entry?;
@@ -72,14 +106,20 @@ impl Core {
 
                 if path.is_dir() {
                     // Try to load the extension
-                    match extension_manager.load_extension(path).await {
-                        Ok(id) => {
+                    match ExtensionManager::load_extension(path, &self.context).await {
+                        Ok((id, extension)) => {
+                            let mut extension_manager = self.context.extension_manager.write()
+                                .map_err(|_| anyhow::anyhow!("Failed to acquire write lock on extension manager"))?;
+
+                            extension_manager.add_extension(id, extension);
                             if let Some(manifest) = extension_manager.get_manifest(id) {
-                                println!("Loaded extension: {} v{}", manifest.name, manifest.version);
+                                info!("Loaded extension: {} v{}", manifest.name, manifest.version);
+                                self.register_keywords(id, manifest);
+                                self.register_fallbacks(id, manifest);
                             }
                         }
                         Err(e) => {
-                            eprintln!("Failed to load extension from {:?}: {}", entry.path(), e);
+                            error!("Failed to load extension from {:?}: {}", entry.path(), e);
                         }
                     }
                 }
@@ -89,6 +129,34 @@ impl Core {
         Ok(())
     }
 
+    fn register_keywords(&self, id: Uuid, manifest: &ExtensionManifest) {
+        let remap = self.context.get_config().keywords;
+        let Ok(mut keywords) = self.context.keywords.write() else {
+            return;
+        };
+
+        for spec in &manifest.keywords {
+            let description = spec
+                .description
+                .clone()
+                .or_else(|| Some(manifest.name.clone()));
+            let arguments = spec.arguments.clone();
+            if let Err(e) = keywords.register(&spec.keyword, id, description, arguments, &remap) {
+                warn!("Extension {} can't use its keyword: {e}", manifest.name);
+            }
+        }
+    }
+
+    fn register_fallbacks(&self, id: Uuid, manifest: &ExtensionManifest) {
+        let Ok(mut fallbacks) = self.context.fallbacks.write() else {
+            return;
+        };
+
+        for spec in &manifest.fallbacks {
+            fallbacks.register(&manifest.name, id, spec.clone());
+        }
+    }
+
     async fn start_event_processing(&self) {
         let mut receiver = self.context.event_bus.subscribe();
         let context = self.context.clone();
@@ -96,7 +164,7 @@ impl Core {
         tokio::spawn(async move {
             while let Ok(event) = receiver.recv().await {
                 if let Err(e) = Self::handle_event(&context, event) {
-                    eprintln!("Error handling event: {e}");
+                    error!("Error handling event: {e}");
                 }
             }
         });
@@ -110,7 +178,283 @@ impl Core {
         Ok(())
     }
 
+    /// Asks every extension for results matching `query` and stores them in
+    /// the state, one section per extension ordered by the providers' scores.
+    /// Queries starting with a keyword only go to the provider that claimed
+    /// it. Results are preceded by the matching items of background jobs, and
+    /// by keyword suggestions while the first word is typed. When no provider has
+    /// anything for a non-empty query, the configured fallbacks are offered
+    /// instead. Extensions that fail are skipped. Returns the sections for the
+    /// current query, which are left untouched when the query has moved on
+    /// while the extensions were answering.
+    pub async fn search(&self, query: String) -> Vec<Section> {
+        // Lists pushed by commands are filtered as the query changes
+        if let Ok(state) = self.context.state.read()
+            && matches!(state.view_mode(), AppMode::List { .. })
+        {
+            return state.sections.clone();
+        }
+
+        let (route, suggestions) = match self.context.keywords.read() {
+            Ok(keywords) => (
+                keywords.route(&query),
+                keywords
+                    .suggestions(&query)
+                    .into_iter()
+                    .map(|keyword| keyword.suggestion())
+                    .collect(),
+            ),
+            Err(_) => (None, Vec::new()),
+        };
+
+        let (extensions, targets, commands) = match self.context.extension_manager.read() {
+            Ok(manager) => {
+                let extensions = manager.extensions();
+                let targets = match &route {
+                    Some(route) => manager.get(route.provider).into_iter().collect(),
+                    None => extensions.clone(),
+                };
+                (extensions, targets, manager.command_items())
+            }
+            Err(_) => (Vec::new(), Vec::new(), Vec::new()),
+        };
+
+        let (provider_query, keyword) = match &route {
+            Some(route) => (route.query.as_str(), Some(route.keyword.as_str())),
+            None => (query.as_str(), None),
+        };
+        let results = futures::future::join_all(
+            targets
+                .iter()
+                .map(|extension| extension.query(provider_query, keyword)),
+        )
+        .await;
+
+        let config = self.context.get_config();
+        let section = |id: &str, title: Option<String>, items: Vec<Item>| {
+            Section::new(id, title, items).with_limit(config.sections.limit(id))
+        };
+
+        // The root list starts with what background jobs found. Other
+        // queries only keep the items they match
+        let mut sections = Vec::new();
+        if route.is_none() {
+            let items = extensions
+                .iter()
+                .flat_map(|extension| extension.background_items())
+                .filter(|item| item.matches(&query))
+                .collect();
+            sections.push(Section::new("background", None, items));
+        }
+        sections.push(section(
+            "keywords",
+            Some("Keywords".to_string()),
+            suggestions,
+        ));
+        if route.is_none() && !query.trim().is_empty() {
+            let items = builtin_items()
+                .into_iter()
+                .chain(commands)
+                .filter(|item| item.matches(&query))
+                .collect();
+            sections.push(section("commands", Some("Commands".to_string()), items));
+        }
+
+        let mut found_any = false;
+        for (extension, result) in targets.iter().zip(results) {
+            let name = &extension.manifest().name;
+            match result {
+                Ok(found) => {
+                    found_any |= !found.is_empty();
+                    // Namespaced so no extension name collides with the
+                    // sections of the launcher itself
+                    let id = format!("ext:{name}");
+                    let mut found = Section::new(&id, Some(name.clone()), found);
+                    found.sort_by_score();
+                    sections.push(found.with_limit(config.sections.limit(&id)));
+                }
+                Err(e) => warn!("Extension {name} failed to search: {e}"),
+            }
+        }
+
+        let trimmed = query.trim();
+        if !found_any
+            && !trimmed.is_empty()
+            && let Ok(fallbacks) = self.context.fallbacks.read()
+        {
+            let items = fallbacks.items(trimmed, &config.fallbacks);
+            sections.push(section("fallbacks", Some("Fallbacks".to_string()), items));
+        }
+        sections.retain(|section| !section.items.is_empty());
+
+        self.resolve_icons(&sections).await;
+
+        match self.context.state.write() {
+            Ok(mut state) => {
+                if state.query == query {
+                    // Searching the same query again, e.g. when background
+                    // items changed, keeps expanded sections expanded
+                    for section in &mut sections {
+                        section.expanded = state
+                            .sections
+                            .iter()
+                            .any(|old| old.id == section.id && old.expanded);
+                    }
+                    state.set_sections(sections);
+                }
+                state.sections.clone()
+            }
+            Err(_) => sections,
+        }
+    }
+
+    /// Replaces the results with `items`, e.g. the results of an action.
+    pub async fn show_results(&self, items: Vec<Item>) -> Vec<Section> {
+        let sections = vec![Section::new("results", None, items)];
+        self.resolve_icons(&sections).await;
+
+        match self.context.state.write() {
+            Ok(mut state) => {
+                state.set_sections(sections);
+                state.sections.clone()
+            }
+            Err(_) => sections,
+        }
+    }
+
+    /// Shows `items` as a list of their own, on top of the current view.
+    pub async fn push_list(&self, title: String, items: Vec<Item>) -> Vec<Section> {
+        let sections = vec![Section::new("list", None, items.clone())];
+        self.resolve_icons(&sections).await;
+
+        match self.context.state.write() {
+            Ok(mut state) => {
+                state.push_list(title, items);
+                state.sections.clone()
+            }
+            Err(_) => sections,
+        }
+    }
+
+    /// Resolves the icons of `sections` off the async runtime, so the UI only
+    /// has to look them up.
+    async fn resolve_icons(&self, sections: &[Section]) {
+        let icons = self.context.icons.clone();
+        let pending = sections.to_vec();
+        if let Err(e) = tokio::task::spawn_blocking(move || icons.resolve_sections(&pending)).await
+        {
+            error!("Failed to resolve icons: {e}");
+        }
+    }
+
+    /// Executes `command` with the handler registered for it.
+    pub async fn execute_command(&self, command: CommandType) -> anyhow::Result<CommandResult> {
+        let handler = self
+            .context
+            .command_registry
+            .read()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on command registry"))?
+            .handler_for(&command)
+            .ok_or_else(|| anyhow::anyhow!("No handler found for command: {:?}", command))?;
+
+        let result = handler.execute(command, &self.context).await?;
+        if let CommandResult::ShowForm(form) = &result
+            && !form.is_submittable()
+        {
+            anyhow::bail!(
+                "Form \"{}\" can't be submitted to {:?}",
+                form.title,
+                form.submit
+            );
+        }
+        Ok(result)
+    }
+
+    /// Runs the action at `action_index` of the item `item_id` among the
+    /// current results, with the arguments typed for the item or after its
+    /// title in the query, after letting everyone know through
+    /// [`AppEvent::ItemActivated`].
+    pub async fn activate(
+        &self,
+        item_id: Uuid,
+        action_index: usize,
+    ) -> anyhow::Result<CommandResult> {
+        let command = {
+            let state = self
+                .context
+                .state
+                .read()
+                .map_err(|_| anyhow::anyhow!("Failed to acquire read lock on state"))?;
+            let item = state
+                .sections
+                .iter()
+                .flat_map(|section| &section.items)
+                .find(|item| item.id == item_id)
+                .ok_or_else(|| anyhow::anyhow!("Item {item_id} is not among the results"))?;
+            let action = item
+                .actions
+                .get(action_index)
+                .ok_or_else(|| anyhow::anyhow!("Item {item_id} has no action {action_index}"))?;
+
+            if item.arguments.is_empty() {
+                action.command_type.clone()
+            } else {
+                // Arguments typed after the item's title count too, e.g. when
+                // "timer 5" is run from the action panel or a shortcut
+                let values = state
+                    .arguments
+                    .as_ref()
+                    .filter(|input| input.item_id == item_id)
+                    .map(|input| input.values.clone())
+                    .or_else(|| item.typed_arguments(&state.query))
+                    .unwrap_or_default();
+                action
+                    .command_type
+                    .with_arguments(&item.arguments, &values)?
+            }
+        };
+
+        let _ = self
+            .context
+            .event_bus
+            .publish(AppEvent::ItemActivated(item_id, action_index));
+
+        self.execute_command(command).await
+    }
+
+    pub fn extension_statuses(&self) -> Vec<(String, ExtensionStatus)> {
+        match self.context.extension_manager.read() {
+            Ok(manager) => manager.statuses(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Recent log entries of every extension, keyed by extension name.
+    pub fn extension_logs(&self) -> Vec<(String, Vec<LogEntry>)> {
+        match self.context.extension_manager.read() {
+            Ok(manager) => manager.logs(),
+            Err(_) => Vec::new(),
+        }
+    }
+
     pub fn context(&self) -> &AppContext {
         &self.context
     }
 }
+
+/// Commands of the launcher itself, found by searching for them.
+fn builtin_items() -> Vec<Item> {
+    vec![Item {
+        title: "Extension logs".to_string(),
+        description: Some("Recent log output of the extensions".to_string()),
+        actions: vec![commands::Action {
+            title: "Show logs".to_string(),
+            description: None,
+            icon: None,
+            command_type: CommandType::ShowExtensionLogs,
+            shortcut: None,
+        }],
+        keywords: vec!["console".to_string(), "debug".to_string()],
+        ..Default::default()
+    }]
+}
@@ -0,0 +1,172 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    str::FromStr,
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::config::LoggingConfig;
+
+/// Installs the application logger. Records at or above the configured level
+/// are written to stderr and to `cognito.log` in the cognito data directory,
+/// which is rotated once it grows past the configured size.
+pub fn init(config: &LoggingConfig) -> anyhow::Result<()> {
+    let level = LevelFilter::from_str(&config.level)
+        .map_err(|_| anyhow::anyhow!("Invalid log level: {}", config.level))?;
+
+    let log_dir = dirs::data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?
+        .join("cognito")
+        .join("logs");
+    std::fs::create_dir_all(&log_dir)?;
+
+    let file = RotatingFile::open(
+        log_dir.join("cognito.log"),
+        config.max_file_size_kb * 1024,
+        config.max_files,
+    )?;
+
+    log::set_boxed_logger(Box::new(Logger {
+        level,
+        file: Mutex::new(file),
+    }))?;
+    log::set_max_level(level);
+
+    Ok(())
+}
+
+struct Logger {
+    level: LevelFilter,
+    file: Mutex<RotatingFile>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = format!(
+            "{} {:<5} [{}] {}\n",
+            humantime::format_rfc3339_seconds(SystemTime::now()),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        eprint!("{line}");
+        if let Ok(mut file) = self.file.lock() {
+            // There is nowhere left to report a failure to write the log
+            let _ = file.write_line(&line);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+/// Log file that is moved to `<name>.1` once it exceeds `max_size` bytes,
+/// shifting older files up and keeping at most `max_files` of them.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_size: u64, max_files: usize) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+            max_files,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+
+        if self.max_files == 0 {
+            self.file = File::create(&self.path)?;
+            self.size = 0;
+            return Ok(());
+        }
+
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                std::fs::rename(from, self.rotated_path(index + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, self.rotated_path(1))?;
+
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{index}"));
+        self.path.with_file_name(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotating_file_keeps_max_files() {
+        let dir = std::env::temp_dir().join(format!("cognito-logs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cognito.log");
+
+        let mut file = RotatingFile::open(path.clone(), 10, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_line(line).unwrap();
+        }
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("cognito.log.1")).unwrap(),
+            "third\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("cognito.log.2")).unwrap(),
+            "second\n"
+        );
+        assert!(!dir.join("cognito.log.3").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
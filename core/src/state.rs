@@ -1,35 +1,874 @@
+use std::{collections::HashSet, path::PathBuf};
+
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::commands::Action;
+use crate::{
+    commands::{Action, Argument, split_arguments},
+    forms::Form,
+    theme::Rgb,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct AppState {
     pub query: String,
-    pub items: Vec<Item>,
+    pub sections: Vec<Section>,
+    /// Index of the selected item among the visible items.
+    pub selected: usize,
+    pub mode: AppMode,
+    /// The views below the current one, root first. Escape returns to the
+    /// last one.
+    pub stack: Vec<Frame>,
+    /// Values typed for the arguments of the selected item.
+    pub arguments: Option<ArgumentInput>,
+}
+
+/// Values typed in the search bar for the arguments of the item `item_id`,
+/// one per argument.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgumentInput {
+    pub item_id: Uuid,
+    pub values: Vec<String>,
+}
+
+/// A view the user navigated away from, kept to return to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
     pub mode: AppMode,
+    pub query: String,
+    pub sections: Vec<Section>,
+    pub selected: usize,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppMode {
     Search,
+    /// A list pushed by a command, e.g. the branches of a repository. The
+    /// query filters its items instead of searching providers.
+    List {
+        title: String,
+        items: Vec<Item>,
+    },
+    /// The preview of `item` on a view of its own, e.g. a whole note.
+    Detail {
+        item: Box<Item>,
+    },
+    /// A form asking for input, see `CommandResult::ShowForm`.
+    Form(Box<Form>),
+    /// The action panel of the item `item_id` is open. `selected` indexes
+    /// the actions matching `filter`.
+    Actions {
+        item_id: Uuid,
+        filter: String,
+        selected: usize,
+    },
+}
+
+/// Ways the selection moves through the visible items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SelectionMove {
+    Up,
+    Down,
+    /// Up or down by this many items, e.g. as many as fit on screen.
+    PageUp(usize),
+    PageDown(usize),
+    First,
+    Last,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Item {
+    /// Nil until the item is shown in a section, which derives an id from
+    /// its provider and title, see `assign_ids`.
+    #[serde(default)]
     pub id: Uuid,
     pub title: String,
+    #[serde(default, alias = "subtitle")]
     pub description: Option<String>,
+    #[serde(default)]
     pub icon: Option<String>,
+    #[serde(default)]
     pub actions: Vec<Action>,
+    /// Values the actions take, typed in the search bar after the item.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<Argument>,
+    /// Short text shown at the end of the row, e.g. a shortcut or a date.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accessories: Vec<Accessory>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub badges: Vec<Badge>,
+    /// Aliases the item is found by without being shown.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Content shown when the item is looked at more closely.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview: Option<Preview>,
+    /// Facts shown under the preview, e.g. a file's size or a snippet's
+    /// language.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metadata: Vec<Metadata>,
+    /// How well the item matches the query according to its provider,
+    /// higher is better. Used to order the items of a section.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Accessory {
+    pub text: String,
+    #[serde(default)]
+    pub tooltip: Option<String>,
+}
+
+/// A tag shown next to the title, e.g. "draft" or "3 new".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Badge {
+    pub text: String,
+    #[serde(default)]
+    pub color: BadgeColor,
+}
+
+/// A named color, or any other string as a hex color like `"#d98c1f"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeColor {
+    #[default]
+    Gray,
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    #[serde(untagged)]
+    Hex(String),
+}
+
+impl BadgeColor {
+    /// The color as RGB, or `None` for hex colors that don't parse.
+    pub fn rgb(&self) -> Option<[u8; 3]> {
+        Some(match self {
+            BadgeColor::Gray => [0x8a, 0x8f, 0x98],
+            BadgeColor::Red => [0xd9, 0x3f, 0x3f],
+            BadgeColor::Orange => [0xd9, 0x8c, 0x1f],
+            BadgeColor::Yellow => [0xd4, 0xb1, 0x06],
+            BadgeColor::Green => [0x3f, 0xa3, 0x4d],
+            BadgeColor::Blue => [0x3b, 0x82, 0xd6],
+            BadgeColor::Purple => [0x8e, 0x5b, 0xd1],
+            BadgeColor::Hex(hex) => Rgb::parse(hex)?.0,
+        })
+    }
+}
+
+/// Quick look content of an item.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Preview {
+    Text {
+        text: String,
+    },
+    Markdown {
+        markdown: String,
+    },
+    /// Path or URL of an image.
+    Image {
+        source: String,
+    },
+    File {
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub label: String,
+    pub value: String,
+}
+
+impl Default for Item {
+    fn default() -> Self {
+        Self {
+            id: Uuid::nil(),
+            title: String::new(),
+            description: None,
+            icon: None,
+            actions: Vec::new(),
+            arguments: Vec::new(),
+            accessories: Vec::new(),
+            badges: Vec::new(),
+            keywords: Vec::new(),
+            preview: None,
+            metadata: Vec::new(),
+            score: None,
+        }
+    }
+}
+
+impl Item {
+    /// Whether every word of `query` appears in the title, description or
+    /// keywords, ignoring case. Items taking arguments also match their
+    /// title followed by arguments, see [`Item::typed_arguments`].
+    pub fn matches(&self, query: &str) -> bool {
+        if self.typed_arguments(query).is_some() {
+            return true;
+        }
+
+        let haystack = std::iter::once(self.title.as_str())
+            .chain(self.description.as_deref())
+            .chain(self.keywords.iter().map(String::as_str))
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| haystack.iter().any(|field| field.contains(word)))
+    }
+
+    /// Values for the item's arguments typed after its title, as in
+    /// "timer 5" for an item "Timer" taking minutes.
+    pub fn typed_arguments(&self, query: &str) -> Option<Vec<String>> {
+        if self.arguments.is_empty() {
+            return None;
+        }
+
+        let query = query.trim_start();
+        let title = query.get(..self.title.len())?;
+        let rest = &query[self.title.len()..];
+        (title.eq_ignore_ascii_case(&self.title) && rest.starts_with(char::is_whitespace))
+            .then(|| split_arguments(rest, self.arguments.len()))
+    }
+}
+
+/// Results grouped by where they came from, e.g. one extension.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Section {
+    /// Stable for the same source across searches, e.g. `ext:<name>` for the
+    /// results of an extension.
+    pub id: String,
+    /// Heading shown above the items. Sections without one are shown as a
+    /// plain list.
+    pub title: Option<String>,
+    pub items: Vec<Item>,
+    /// Number of items shown until the section is expanded.
+    pub limit: Option<usize>,
+    /// Whether "show more" was chosen, lifting the limit.
+    #[serde(default)]
+    pub expanded: bool,
+}
+
+impl Section {
+    pub fn new(id: impl Into<String>, title: Option<String>, mut items: Vec<Item>) -> Self {
+        let id = id.into();
+        assign_ids(&id, &mut items);
+        Self {
+            id,
+            title,
+            items,
+            limit: None,
+            expanded: false,
+        }
+    }
+
+    /// Orders the items by their score, best first. Items without one keep
+    /// their place relative to each other after the scored ones.
+    pub fn sort_by_score(&mut self) {
+        self.items.sort_by(|a, b| match (a.score, b.score) {
+            (Some(a), Some(b)) => b.total_cmp(&a),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+    }
+
+    pub fn with_limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// The items shown, taking the limit into account.
+    pub fn visible_items(&self) -> &[Item] {
+        match self.limit {
+            Some(limit) if !self.expanded => &self.items[..limit.min(self.items.len())],
+            _ => &self.items,
+        }
+    }
+
+    /// Number of items behind "show more".
+    pub fn hidden_count(&self) -> usize {
+        self.items.len() - self.visible_items().len()
+    }
+}
+
+impl AppMode {
+    /// Name of the mode in the breadcrumbs, or `None` for modes shown on
+    /// top of another one.
+    pub fn title(&self) -> Option<&str> {
+        match self {
+            AppMode::Search => Some("Search"),
+            AppMode::List { title, .. } => Some(title),
+            AppMode::Detail { item } => Some(&item.title),
+            AppMode::Form(form) => Some(&form.title),
+            AppMode::Actions { .. } => None,
+        }
+    }
+}
+
+impl AppState {
+    /// The items shown, in display order.
+    pub fn visible_items(&self) -> impl Iterator<Item = &Item> {
+        self.sections.iter().flat_map(Section::visible_items)
+    }
+
+    pub fn selected_item(&self) -> Option<&Item> {
+        self.visible_items().nth(self.selected)
+    }
+
+    /// Replaces the sections, keeping the selected item selected when it is
+    /// still visible, and the selected position otherwise.
+    pub fn set_sections(&mut self, sections: Vec<Section>) {
+        let selected = self.selected_item().map(|item| item.id);
+        self.sections = sections;
+        match selected.and_then(|id| self.visible_items().position(|item| item.id == id)) {
+            Some(index) => self.selected = index,
+            None => self.select(self.selected),
+        }
+        if self.actions_item().is_none() {
+            self.close_actions();
+        }
+        if self.arguments_item().is_none() {
+            self.arguments = None;
+        }
+    }
+
+    /// Lifts the limit of the section `id`.
+    pub fn expand_section(&mut self, id: &str) {
+        let mut sections = self.sections.clone();
+        if let Some(section) = sections.iter_mut().find(|section| section.id == id) {
+            section.expanded = true;
+        }
+        self.set_sections(sections);
+    }
+
+    /// Selects the item at `index`, or the last one when there are fewer.
+    pub fn select(&mut self, index: usize) {
+        let count = self.visible_items().count();
+        self.selected = index.min(count.saturating_sub(1));
+        if self.arguments_item().is_none() {
+            self.arguments = None;
+        }
+    }
+
+    /// Moves the selection, wrapping around when moving up from the first
+    /// item or down from the last one. While the action panel is open, the
+    /// selection moves through its actions instead.
+    pub fn move_selection(&mut self, movement: SelectionMove) {
+        match &self.mode {
+            AppMode::Search | AppMode::List { .. } => {
+                let count = self.visible_items().count();
+                self.select(moved(self.selected, count, movement));
+            }
+            AppMode::Actions { .. } => {
+                let count = self.filtered_actions().len();
+                if let AppMode::Actions { selected, .. } = &mut self.mode {
+                    *selected = moved(*selected, count, movement);
+                }
+            }
+            // Views without results have nothing to select
+            AppMode::Detail { .. } | AppMode::Form(_) => {}
+        }
+    }
+
+    /// Opens the action panel of the selected item. Returns `false` when no
+    /// item is selected or it has no actions.
+    pub fn open_actions(&mut self) -> bool {
+        let Some(item) = self.selected_item().filter(|item| !item.actions.is_empty()) else {
+            return false;
+        };
+
+        self.push(AppMode::Actions {
+            item_id: item.id,
+            filter: String::new(),
+            selected: 0,
+        });
+        true
+    }
+
+    pub fn close_actions(&mut self) {
+        if !matches!(self.mode, AppMode::Actions { .. }) {
+            return;
+        }
+        // The panel is drawn over the view below, which stays as it is now
+        if let Some(frame) = self.stack.pop() {
+            self.mode = frame.mode;
+        }
+    }
+
+    /// Starts typing the arguments of the selected item, with the values
+    /// already typed after its title. Returns `false` when it takes none.
+    pub fn open_arguments(&mut self) -> bool {
+        let Some(item) = self
+            .selected_item()
+            .filter(|item| !item.arguments.is_empty())
+        else {
+            return false;
+        };
+
+        let values = item
+            .typed_arguments(&self.query)
+            .unwrap_or_else(|| vec![String::new(); item.arguments.len()]);
+        self.arguments = Some(ArgumentInput {
+            item_id: item.id,
+            values,
+        });
+        true
+    }
+
+    pub fn set_argument(&mut self, index: usize, value: String) {
+        if let Some(slot) = self
+            .arguments
+            .as_mut()
+            .and_then(|input| input.values.get_mut(index))
+        {
+            *slot = value;
+        }
+    }
+
+    /// The selected item, while its arguments are typed.
+    pub fn arguments_item(&self) -> Option<&Item> {
+        let input = self.arguments.as_ref()?;
+        self.selected_item().filter(|item| item.id == input.item_id)
+    }
+
+    /// Switches to `mode`, keeping the current view to return to. The new
+    /// view starts with the current query and results.
+    pub fn push(&mut self, mode: AppMode) {
+        let mode = std::mem::replace(&mut self.mode, mode);
+        self.stack.push(Frame {
+            mode,
+            query: self.query.clone(),
+            sections: self.sections.clone(),
+            selected: self.selected,
+        });
+    }
+
+    /// Shows `mode` on top of the current view, starting with an empty
+    /// query and no results of its own.
+    pub fn push_view(&mut self, mode: AppMode) {
+        self.close_actions();
+        self.push(mode);
+        self.query.clear();
+        self.sections = Vec::new();
+        self.selected = 0;
+        self.arguments = None;
+    }
+
+    /// Shows `items` as a list of their own, filtered by the query.
+    pub fn push_list(&mut self, title: String, mut items: Vec<Item>) {
+        // Items keep their ids however the list is filtered
+        assign_ids("list", &mut items);
+        self.push_view(AppMode::List { title, items });
+        self.filter_list();
+    }
+
+    /// Returns to the previous view. Returns `false` at the root.
+    pub fn pop(&mut self) -> bool {
+        let Some(frame) = self.stack.pop() else {
+            return false;
+        };
+
+        self.mode = frame.mode;
+        self.query = frame.query;
+        self.sections = frame.sections;
+        self.selected = frame.selected;
+        self.arguments = None;
+        true
+    }
+
+    /// Returns to the root view.
+    pub fn pop_to_root(&mut self) {
+        while self.pop() {}
+    }
+
+    /// Titles of the views down to the current one, root first.
+    pub fn breadcrumbs(&self) -> Vec<&str> {
+        self.stack
+            .iter()
+            .map(|frame| &frame.mode)
+            .chain(std::iter::once(&self.mode))
+            .filter_map(AppMode::title)
+            .collect()
+    }
+
+    /// The mode of the view shown, under the action panel if it is open.
+    pub fn view_mode(&self) -> &AppMode {
+        match (&self.mode, self.stack.last()) {
+            (AppMode::Actions { .. }, Some(frame)) => &frame.mode,
+            (mode, _) => mode,
+        }
+    }
+
+    /// Shows the items of a pushed list that match the query. Returns
+    /// `false` when no list is shown, i.e. results come from providers.
+    pub fn filter_list(&mut self) -> bool {
+        let AppMode::List { items, .. } = self.view_mode() else {
+            return false;
+        };
+
+        let items = items
+            .iter()
+            .filter(|item| item.matches(&self.query))
+            .cloned()
+            .collect();
+        self.set_sections(vec![Section::new("list", None, items)]);
+        true
+    }
+
+    pub fn set_action_filter(&mut self, new_filter: String) {
+        if let AppMode::Actions {
+            filter, selected, ..
+        } = &mut self.mode
+        {
+            *filter = new_filter;
+            *selected = 0;
+        }
+    }
+
+    /// The item whose action panel is open.
+    pub fn actions_item(&self) -> Option<&Item> {
+        let AppMode::Actions { item_id, .. } = &self.mode else {
+            return None;
+        };
+        self.sections
+            .iter()
+            .flat_map(|section| &section.items)
+            .find(|item| item.id == *item_id)
+    }
+
+    /// The actions of the open action panel that match its filter, with
+    /// their index among all of the item's actions.
+    pub fn filtered_actions(&self) -> Vec<(usize, &Action)> {
+        let (Some(item), AppMode::Actions { filter, .. }) = (self.actions_item(), &self.mode)
+        else {
+            return Vec::new();
+        };
+
+        item.actions
+            .iter()
+            .enumerate()
+            .filter(|(_, action)| action.matches(filter))
+            .collect()
+    }
+
+    /// The item and index of the action selected in the action panel.
+    pub fn selected_action(&self) -> Option<(Uuid, usize)> {
+        let AppMode::Actions {
+            item_id, selected, ..
+        } = &self.mode
+        else {
+            return None;
+        };
+
+        let (index, _) = self.filtered_actions().get(*selected).copied()?;
+        Some((*item_id, index))
+    }
+}
+
+/// `selected` after `movement` through `count` entries.
+fn moved(selected: usize, count: usize, movement: SelectionMove) -> usize {
+    let Some(last) = count.checked_sub(1) else {
+        return 0;
+    };
+
+    match movement {
+        SelectionMove::Up if selected == 0 => last,
+        SelectionMove::Up => selected - 1,
+        SelectionMove::Down if selected >= last => 0,
+        SelectionMove::Down => selected + 1,
+        SelectionMove::PageUp(page) => selected.saturating_sub(page.max(1)),
+        SelectionMove::PageDown(page) => (selected + page.max(1)).min(last),
+        SelectionMove::First => 0,
+        SelectionMove::Last => last,
+    }
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
             query: Default::default(),
-            items: Default::default(),
+            sections: Default::default(),
+            selected: 0,
             mode: AppMode::Search,
+            stack: Vec::new(),
+            arguments: None,
         }
     }
 }
+
+/// Gives the `items` of `provider` that came without an id one derived
+/// from their title, so they can be found again by a later search, e.g.
+/// from the command line. Items with the same title are told apart by
+/// their order.
+pub fn assign_ids(provider: &str, items: &mut [Item]) {
+    let mut taken = HashSet::new();
+    for item in items.iter_mut().filter(|item| item.id.is_nil()) {
+        let name = format!("{provider}\0{}", item.title);
+        let mut id = Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes());
+        for duplicate in 1.. {
+            if taken.insert(id) {
+                break;
+            }
+            let name = format!("{name}\0{duplicate}");
+            id = Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes());
+        }
+        item.id = id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandType;
+
+    fn item(title: &str) -> Item {
+        Item {
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_section_limit_and_expansion() {
+        let items = vec![item("a"), item("b"), item("c")];
+        let mut section = Section::new("files", None, items).with_limit(Some(2));
+
+        assert_eq!(section.visible_items().len(), 2);
+        assert_eq!(section.hidden_count(), 1);
+
+        section.expanded = true;
+        assert_eq!(section.visible_items().len(), 3);
+        assert_eq!(section.hidden_count(), 0);
+
+        // A limit above the number of items hides nothing
+        let section = Section::new("apps", None, vec![item("a")]).with_limit(Some(5));
+        assert_eq!(section.hidden_count(), 0);
+    }
+
+    #[test]
+    fn test_item_from_extension_json() {
+        let item: Item = serde_json::from_value(serde_json::json!({
+            "title": "Fix login redirect",
+            "subtitle": "cognito#42",
+            "accessories": [{ "text": "2d" }],
+            "badges": [{ "text": "draft" }, { "text": "ci", "color": "#3fa34d" }],
+            "keywords": ["pr", "pull request"],
+            "preview": { "type": "markdown", "markdown": "# Fix" },
+            "metadata": [{ "label": "Author", "value": "ada" }],
+            "score": 0.8,
+        }))
+        .unwrap();
+
+        assert_eq!(item.description.as_deref(), Some("cognito#42"));
+        assert_eq!(item.badges[0].color, BadgeColor::Gray);
+        assert_eq!(item.badges[1].color.rgb(), Some([0x3f, 0xa3, 0x4d]));
+        assert!(matches!(item.preview, Some(Preview::Markdown { .. })));
+        assert_eq!(item.metadata[0].value, "ada");
+        assert!(item.matches("pull LOGIN"));
+        assert!(!item.matches("issue"));
+    }
+
+    #[test]
+    fn test_items_get_stable_ids() {
+        let items = || vec![item("README.md"), item("README.md"), item("LICENSE")];
+        let first = Section::new("files", None, items());
+        let again = Section::new("files", None, items());
+        let other = Section::new("ext:notes", None, items());
+
+        let ids = |section: &Section| -> Vec<Uuid> {
+            let ids = section.items.iter().map(|item| item.id);
+            ids.collect()
+        };
+        assert_eq!(ids(&first), ids(&again));
+        assert_ne!(ids(&first)[0], ids(&other)[0]);
+        assert_ne!(ids(&first)[0], ids(&first)[1]);
+
+        // Ids given by the provider are kept
+        let given = Uuid::new_v4();
+        let provided = Item {
+            id: given,
+            ..item("a")
+        };
+        let section = Section::new("files", None, vec![provided]);
+        assert_eq!(section.items[0].id, given);
+    }
+
+    #[test]
+    fn test_sort_by_score() {
+        let scored = |title: &str, score| Item {
+            title: title.to_string(),
+            score,
+            ..Default::default()
+        };
+        let mut section = Section::new(
+            "files",
+            None,
+            vec![
+                scored("a", None),
+                scored("b", Some(0.2)),
+                scored("c", None),
+                scored("d", Some(0.9)),
+            ],
+        );
+
+        section.sort_by_score();
+        let titles: Vec<_> = section
+            .items
+            .iter()
+            .map(|item| item.title.as_str())
+            .collect();
+        assert_eq!(titles, ["d", "b", "a", "c"]);
+    }
+
+    #[test]
+    fn test_selection_follows_items() {
+        let items: Vec<_> = ["a", "b", "c", "d"].into_iter().map(item).collect();
+        let mut state = AppState::default();
+        state.set_sections(vec![
+            Section::new("files", None, items[..3].to_vec()).with_limit(Some(2)),
+            Section::new("apps", None, items[3..].to_vec()),
+        ]);
+
+        // "c" is hidden behind "show more", so "d" follows "b"
+        state.move_selection(SelectionMove::Down);
+        state.move_selection(SelectionMove::Down);
+        assert_eq!(state.selected_item().unwrap().title, "d");
+        state.move_selection(SelectionMove::Down);
+        assert_eq!(state.selected, 0);
+        state.move_selection(SelectionMove::Up);
+        assert_eq!(state.selected_item().unwrap().title, "d");
+        state.move_selection(SelectionMove::PageUp(10));
+        assert_eq!(state.selected, 0);
+
+        // Expanding keeps the selected item selected
+        state.move_selection(SelectionMove::Last);
+        state.expand_section("files");
+        assert_eq!(state.selected_item().unwrap().title, "d");
+        assert_eq!(state.selected, 3);
+
+        state.select(10);
+        assert_eq!(state.selected, 3);
+    }
+
+    #[test]
+    fn test_action_panel_filters_actions() {
+        let action = |title: &str| Action {
+            title: title.to_string(),
+            description: None,
+            icon: None,
+            command_type: CommandType::SetQuery {
+                query: String::new(),
+            },
+            shortcut: None,
+        };
+        let mut state = AppState::default();
+        state.set_sections(vec![Section::new(
+            "files",
+            None,
+            vec![Item {
+                title: "notes.md".to_string(),
+                actions: vec![action("Open"), action("Copy path"), action("Copy name")],
+                ..Default::default()
+            }],
+        )]);
+        let item_id = state.sections[0].items[0].id;
+
+        assert!(state.open_actions());
+        state.set_action_filter("copy".to_string());
+        assert_eq!(state.filtered_actions().len(), 2);
+        state.move_selection(SelectionMove::Down);
+        assert_eq!(state.selected_action(), Some((item_id, 2)));
+
+        state.close_actions();
+        assert_eq!(state.selected_action(), None);
+    }
+
+    #[test]
+    fn test_typed_arguments() {
+        let timer = Item {
+            title: "Timer".to_string(),
+            arguments: vec![Argument {
+                name: "minutes".to_string(),
+                placeholder: None,
+                required: true,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            timer.typed_arguments("timer 5"),
+            Some(vec!["5".to_string()])
+        );
+        assert!(timer.matches("timer 5"));
+        assert_eq!(timer.typed_arguments("timers"), None);
+
+        let mut state = AppState {
+            query: "timer 5".to_string(),
+            ..Default::default()
+        };
+        state.set_sections(vec![Section::new("tools", None, vec![timer, item("Todo")])]);
+        assert!(state.open_arguments());
+        assert_eq!(state.arguments.as_ref().unwrap().values, ["5"]);
+        state.set_argument(0, "10".to_string());
+        assert_eq!(state.arguments.as_ref().unwrap().values, ["10"]);
+
+        // Moving on to another item drops what was typed
+        state.move_selection(SelectionMove::Down);
+        assert_eq!(state.arguments, None);
+        assert!(!state.open_arguments());
+    }
+
+    #[test]
+    fn test_navigation_stack() {
+        let mut state = AppState {
+            query: "git".to_string(),
+            ..Default::default()
+        };
+        state.set_sections(vec![Section::new("repos", None, vec![item("cognito")])]);
+
+        let branches = vec![item("main"), item("feature/lists")];
+        state.push_list("cognito".to_string(), branches);
+        assert_eq!(state.breadcrumbs(), ["Search", "cognito"]);
+        assert_eq!(state.query, "");
+        assert_eq!(state.visible_items().count(), 2);
+
+        state.query = "feat".to_string();
+        assert!(state.filter_list());
+        assert_eq!(state.selected_item().unwrap().title, "feature/lists");
+
+        // The action panel doesn't count as a view of its own
+        state.sections[0].items[0].actions = vec![Action {
+            title: "Checkout".to_string(),
+            description: None,
+            icon: None,
+            command_type: CommandType::SetQuery {
+                query: String::new(),
+            },
+            shortcut: None,
+        }];
+        assert!(state.open_actions());
+        assert_eq!(state.breadcrumbs(), ["Search", "cognito"]);
+        assert!(matches!(state.view_mode(), AppMode::List { .. }));
+        state.close_actions();
+
+        // Previews and forms are views like lists, with nothing to select
+        state.push_view(AppMode::Detail {
+            item: Box::new(item("feature/lists")),
+        });
+        assert_eq!(state.breadcrumbs(), ["Search", "cognito", "feature/lists"]);
+        assert!(state.selected_item().is_none());
+        assert!(state.pop());
+        assert_eq!(state.breadcrumbs(), ["Search", "cognito"]);
+
+        assert!(state.pop());
+        assert_eq!(state.query, "git");
+        assert_eq!(state.selected_item().unwrap().title, "cognito");
+        assert!(!state.pop());
+    }
+}
@@ -0,0 +1,329 @@
+use std::{fmt, path::Path};
+
+use futures::{Stream, StreamExt};
+use log::warn;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zbus::zvariant::{OwnedValue, Value};
+
+use crate::config::{self, ThemeConfig};
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const PORTAL_SETTINGS: &str = "org.freedesktop.portal.Settings";
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+
+/// How the launcher looks. Themes are TOML files in the `themes` config
+/// directory, named after the theme. A file only lists what it changes
+/// about its `base`, one of the built-in `light` and `dark` themes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThemeDefinition {
+    pub name: String,
+    pub dark: bool,
+    pub colors: ThemeColors,
+    pub selected_row: SelectedRow,
+    pub font: FontSettings,
+    /// Radius of rounded corners, in pixels.
+    pub corner_radius: f32,
+    /// Space between the parts of a screen, in pixels.
+    pub spacing: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThemeColors {
+    pub background: Rgb,
+    /// Background of panels drawn over the results, e.g. the action panel.
+    pub surface: Rgb,
+    pub text: Rgb,
+    pub accent: Rgb,
+    pub border: Rgb,
+    pub success: Rgb,
+    pub danger: Rgb,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectedRow {
+    pub background: Rgb,
+    pub text: Rgb,
+    /// Outline of the row, none when not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border: Option<Rgb>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FontSettings {
+    /// Family of the font used for all text, the default font when not
+    /// given. Only read at startup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family: Option<String>,
+    pub size: u16,
+}
+
+/// A color, written as `"#rrggbb"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub [u8; 3]);
+
+impl Rgb {
+    pub fn parse(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Self([channel(0)?, channel(2)?, channel(4)?]))
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+impl Serialize for Rgb {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rgb {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Rgb::parse(&hex)
+            .ok_or_else(|| serde::de::Error::custom(format!("'{hex}' is not a #rrggbb color")))
+    }
+}
+
+impl ThemeDefinition {
+    pub fn light() -> Self {
+        Self {
+            name: "light".to_string(),
+            dark: false,
+            colors: ThemeColors {
+                background: Rgb([0xfa, 0xfa, 0xfb]),
+                surface: Rgb([0xff, 0xff, 0xff]),
+                text: Rgb([0x1f, 0x23, 0x28]),
+                accent: Rgb([0x3b, 0x82, 0xd6]),
+                border: Rgb([0xd8, 0xdb, 0xe0]),
+                success: Rgb([0x3f, 0xa3, 0x4d]),
+                danger: Rgb([0xd9, 0x3f, 0x3f]),
+            },
+            selected_row: SelectedRow {
+                background: Rgb([0xe6, 0xec, 0xf5]),
+                text: Rgb([0x1f, 0x23, 0x28]),
+                border: None,
+            },
+            font: FontSettings {
+                family: None,
+                size: 16,
+            },
+            corner_radius: 6.0,
+            spacing: 10,
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".to_string(),
+            dark: true,
+            colors: ThemeColors {
+                background: Rgb([0x1e, 0x1f, 0x24]),
+                surface: Rgb([0x27, 0x29, 0x30]),
+                text: Rgb([0xe6, 0xe7, 0xea]),
+                accent: Rgb([0x5b, 0x9b, 0xe6]),
+                border: Rgb([0x3a, 0x3d, 0x46]),
+                success: Rgb([0x4c, 0xb8, 0x5b]),
+                danger: Rgb([0xe5, 0x5b, 0x5b]),
+            },
+            selected_row: SelectedRow {
+                background: Rgb([0x33, 0x37, 0x42]),
+                text: Rgb([0xf4, 0xf5, 0xf7]),
+                border: None,
+            },
+            font: FontSettings {
+                family: None,
+                size: 16,
+            },
+            corner_radius: 6.0,
+            spacing: 10,
+        }
+    }
+
+    /// The built-in theme `name`.
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(Self::light()),
+            "dark" => Some(Self::dark()),
+            _ => None,
+        }
+    }
+
+    /// The theme `name`, from `<name>.toml` in `themes_dir` or built in. A
+    /// file wins over the built-in theme of the same name.
+    pub fn load(name: &str, themes_dir: &Path) -> anyhow::Result<Self> {
+        let path = themes_dir.join(format!("{name}.toml"));
+        if !path.exists() {
+            return Self::built_in(name).ok_or_else(|| {
+                anyhow::anyhow!("Theme '{name}' not found in {}", themes_dir.display())
+            });
+        }
+
+        let mut theme = Self::parse(&std::fs::read_to_string(&path)?)?;
+        theme.name = name.to_string();
+        Ok(theme)
+    }
+
+    /// A theme file: a `base` theme (`light` unless given) with the values
+    /// of the file on top.
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let mut file: toml::Table = toml::from_str(content)?;
+        let base = match file.remove("base") {
+            Some(toml::Value::String(base)) => base,
+            Some(_) => anyhow::bail!("'base' must be the name of a built-in theme"),
+            None => "light".to_string(),
+        };
+        let base = Self::built_in(&base)
+            .ok_or_else(|| anyhow::anyhow!("'{base}' is not a built-in theme"))?;
+
+        let mut merged = toml::Table::try_from(base)?;
+        merge(&mut merged, file);
+        Ok(merged.try_into()?)
+    }
+}
+
+/// Sets the values of `overlay` in `table`, merging nested tables.
+fn merge(table: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(nested)), toml::Value::Table(overlay)) => {
+                merge(nested, overlay)
+            }
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
+/// The theme `config` asks for. `prefers_dark` is the desktop's preference
+/// as far as it is known. Themes that fail to load are logged and replaced
+/// with a built-in one.
+pub fn configured(config: &ThemeConfig, prefers_dark: Option<bool>) -> ThemeDefinition {
+    let name = config.name(prefers_dark);
+    let loaded =
+        config::config_dir().and_then(|dir| ThemeDefinition::load(name, &dir.join("themes")));
+
+    loaded.unwrap_or_else(|e| {
+        warn!("Failed to load theme '{name}': {e}");
+        match prefers_dark {
+            Some(false) => ThemeDefinition::light(),
+            _ => ThemeDefinition::dark(),
+        }
+    })
+}
+
+/// Whether the desktop prefers dark colors, according to the freedesktop
+/// settings portal. `None` when it has no preference, or there is no
+/// portal to ask.
+pub fn system_prefers_dark() -> Option<bool> {
+    let read = || -> zbus::Result<OwnedValue> {
+        let connection = zbus::blocking::Connection::session()?;
+        let proxy = zbus::blocking::Proxy::new(
+            &connection,
+            PORTAL_DESTINATION,
+            PORTAL_PATH,
+            PORTAL_SETTINGS,
+        )?;
+        proxy.call("Read", &(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY))
+    };
+
+    match read() {
+        Ok(value) => prefers_dark(&value),
+        Err(e) => {
+            warn!("Failed to read the color scheme from the settings portal: {e}");
+            None
+        }
+    }
+}
+
+/// Whether the desktop prefers dark colors, each time its preference
+/// changes. `None` when it no longer has a preference.
+pub async fn color_scheme_changes()
+-> anyhow::Result<impl Stream<Item = Option<bool>> + Send + 'static> {
+    let connection = zbus::Connection::session().await?;
+    let proxy = zbus::Proxy::new(
+        &connection,
+        PORTAL_DESTINATION,
+        PORTAL_PATH,
+        PORTAL_SETTINGS,
+    )
+    .await?;
+    let signals = proxy.receive_signal("SettingChanged").await?;
+
+    Ok(signals.filter_map(|message| async move {
+        let (namespace, key, value): (String, String, OwnedValue) =
+            message.body().deserialize().ok()?;
+        if namespace != APPEARANCE_NAMESPACE || key != COLOR_SCHEME_KEY {
+            return None;
+        }
+        Some(prefers_dark(&value))
+    }))
+}
+
+/// The portal's `color-scheme` setting, which may come wrapped in
+/// variants: 1 prefers dark, 2 prefers light and 0 has no preference.
+fn prefers_dark(value: &Value) -> Option<bool> {
+    match value {
+        Value::Value(inner) => prefers_dark(inner),
+        Value::U32(1) => Some(true),
+        Value::U32(2) => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_file_extends_base() {
+        let theme = ThemeDefinition::parse(
+            r##"
+            base = "dark"
+            corner_radius = 0.0
+
+            [colors]
+            accent = "#ff8800"
+
+            [selected_row]
+            border = "#ff8800"
+            "##,
+        )
+        .unwrap();
+
+        assert!(theme.dark);
+        assert_eq!(theme.corner_radius, 0.0);
+        assert_eq!(theme.colors.accent, Rgb([0xff, 0x88, 0x00]));
+        assert_eq!(
+            theme.colors.background,
+            ThemeDefinition::dark().colors.background
+        );
+        assert_eq!(theme.selected_row.border, Some(Rgb([0xff, 0x88, 0x00])));
+
+        assert!(ThemeDefinition::parse("[colors]\ntext = \"black\"").is_err());
+        assert!(ThemeDefinition::parse("base = \"solarized\"").is_err());
+    }
+
+    #[test]
+    fn test_portal_color_scheme() {
+        assert_eq!(prefers_dark(&Value::U32(1)), Some(true));
+        assert_eq!(prefers_dark(&Value::U32(2)), Some(false));
+        assert_eq!(prefers_dark(&Value::U32(0)), None);
+        assert_eq!(
+            prefers_dark(&Value::Value(Box::new(Value::U32(1)))),
+            Some(true)
+        );
+        assert_eq!(prefers_dark(&Value::from("dark")), None);
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use clap::{Parser, Subcommand};
+use core::{
+    Core,
+    commands::CommandResult,
+    config::{self, AppConfig},
+    daemon::{self, DaemonCommand},
+    extensions::ExtensionManager,
+    state::{Item, Section},
+};
+use uuid::Uuid;
+
+/// A keyboard launcher. Without a command, shows the launcher.
+#[derive(Debug, Parser)]
+#[command(name = "cognito", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Show the launcher
+    Show {
+        /// Search for this text
+        #[arg(long)]
+        query: Option<String>,
+    },
+    /// Show the launcher, or hide it when it is shown
+    Toggle,
+    /// Quit the running instance
+    Quit,
+    /// Print the results for a query, without showing the launcher
+    Query {
+        #[arg(required = true)]
+        text: Vec<String>,
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run an action of an item among the results for a query. Item ids are
+    /// those printed by `query`, for items whose extension gives them one
+    Run {
+        item_id: Uuid,
+        /// Index of the action, the item's default action when not given
+        #[arg(long, default_value_t = 0)]
+        action: usize,
+        /// The query the item is among the results of
+        #[arg(long, default_value = "")]
+        query: String,
+    },
+    /// Manage extensions
+    #[command(subcommand)]
+    Ext(ExtCommand),
+    /// Read and change settings in config.toml
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ExtCommand {
+    /// List the installed extensions
+    List,
+    /// Install the extension in a directory, by copying it
+    Install { path: PathBuf },
+    /// Remove an installed extension
+    Remove { name: String },
+    /// Have the running instance load the installed extensions again
+    Reload,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print a setting, e.g. `window.width`
+    Get { key: String },
+    /// Change a setting. Takes effect when Cognito starts
+    Set { key: String, value: String },
+}
+
+/// Runs the commands that don't need the launcher.
+pub fn run(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Query { text, json } => block_on(query(text.join(" "), json)),
+        Command::Run {
+            item_id,
+            action,
+            query,
+        } => block_on(run_item(item_id, action, query)),
+        Command::Ext(ExtCommand::List) => list_extensions(),
+        Command::Ext(ExtCommand::Install { path }) => install_extension(&path),
+        Command::Ext(ExtCommand::Remove { name }) => remove_extension(&name),
+        Command::Ext(ExtCommand::Reload) => {
+            if !daemon::send(&DaemonCommand::ReloadExtensions)? {
+                anyhow::bail!("Cognito is not running");
+            }
+            Ok(())
+        }
+        Command::Config(ConfigCommand::Get { key }) => {
+            match AppConfig::load()?.get(&key)? {
+                toml::Value::String(value) => println!("{value}"),
+                toml::Value::Table(table) => print!("{}", toml::to_string_pretty(&table)?),
+                value => println!("{value}"),
+            }
+            Ok(())
+        }
+        Command::Config(ConfigCommand::Set { key, value }) => {
+            let mut config = AppConfig::load()?;
+            config.set(&key, &value)?;
+            config.save()
+        }
+        Command::Show { .. } | Command::Toggle | Command::Quit => {
+            anyhow::bail!("{command:?} is sent to the running instance")
+        }
+    }
+}
+
+fn block_on<F: Future<Output = anyhow::Result<()>>>(future: F) -> anyhow::Result<()> {
+    tokio::runtime::Runtime::new()?.block_on(future)
+}
+
+/// A core with the installed extensions loaded.
+async fn start_core() -> anyhow::Result<Core> {
+    let mut core = Core::new()?;
+    core.start().await?;
+    Ok(core)
+}
+
+/// Results for `query`, stored in the state like the launcher does, so
+/// they are kept and their items can be run.
+async fn search(core: &Core, query: String) -> anyhow::Result<Vec<Section>> {
+    core.context().handle_query(query.clone())?;
+    Ok(core.search(query).await)
+}
+
+async fn query(text: String, json: bool) -> anyhow::Result<()> {
+    let core = start_core().await?;
+    let sections = search(&core, text).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&sections)?);
+    } else {
+        print_sections(&sections);
+    }
+    Ok(())
+}
+
+async fn run_item(item_id: Uuid, action: usize, query: String) -> anyhow::Result<()> {
+    let core = start_core().await?;
+    search(&core, query).await?;
+
+    match core.activate(item_id, action).await? {
+        CommandResult::Success => {}
+        CommandResult::ShowResults(items) | CommandResult::PushList { items, .. } => {
+            items.iter().for_each(print_item)
+        }
+        CommandResult::ShowNotification(message) | CommandResult::SetQuery(message) => {
+            println!("{message}")
+        }
+        CommandResult::ShowDetail(item) => print_item(&item),
+        CommandResult::ShowForm(form) => {
+            anyhow::bail!(
+                "\"{}\" asks for input, run it from the launcher",
+                form.title
+            )
+        }
+        CommandResult::ShowExtensionLogs => {
+            anyhow::bail!("Extension logs are only kept by the launcher")
+        }
+        CommandResult::Error(message) => anyhow::bail!(message),
+    }
+    Ok(())
+}
+
+fn print_sections(sections: &[Section]) {
+    for section in sections {
+        if let Some(title) = &section.title {
+            println!("{title}");
+        }
+        section.items.iter().for_each(print_item);
+    }
+}
+
+fn print_item(item: &Item) {
+    match &item.description {
+        Some(description) => println!("  {}  {}  {description}", item.id, item.title),
+        None => println!("  {}  {}", item.id, item.title),
+    }
+}
+
+fn list_extensions() -> anyhow::Result<()> {
+    for (path, manifest) in installed_extensions()? {
+        println!("{} {}  {}", manifest.name, manifest.version, path.display());
+    }
+    Ok(())
+}
+
+fn install_extension(path: &Path) -> anyhow::Result<()> {
+    let manifest = ExtensionManager::read_manifest(path)
+        .map_err(|e| anyhow::anyhow!("No extension in {}: {e}", path.display()))?;
+    // The name becomes the directory it is installed in
+    if manifest.name.is_empty() || manifest.name.starts_with('.') || manifest.name.contains('/') {
+        anyhow::bail!("'{}' is not a valid extension name", manifest.name);
+    }
+    let target = config::extensions_dir()?.join(&manifest.name);
+    if target.exists() {
+        anyhow::bail!("{} is already installed", manifest.name);
+    }
+
+    copy_dir(path, &target)?;
+    println!("Installed {} {}", manifest.name, manifest.version);
+    reload_running()
+}
+
+fn remove_extension(name: &str) -> anyhow::Result<()> {
+    if name.is_empty() {
+        anyhow::bail!("No extension given");
+    }
+    let (path, _) = installed_extensions()?
+        .into_iter()
+        .find(|(path, manifest)| {
+            manifest.name == name || path.file_name() == Some(OsStr::new(name))
+        })
+        .ok_or_else(|| anyhow::anyhow!("{name} is not installed"))?;
+
+    std::fs::remove_dir_all(&path)?;
+    println!("Removed {name}");
+    reload_running()
+}
+
+/// The directory and manifest of every installed extension. Directories
+/// without a valid manifest are skipped.
+fn installed_extensions() -> anyhow::Result<Vec<(PathBuf, core::extensions::ExtensionManifest)>> {
+    let dir = config::extensions_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut extensions = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if let Ok(manifest) = ExtensionManager::read_manifest(&path) {
+            extensions.push((path, manifest));
+        }
+    }
+    extensions.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+    Ok(extensions)
+}
+
+/// Lets the running instance, if any, pick up installed or removed
+/// extensions.
+fn reload_running() -> anyhow::Result<()> {
+    daemon::send(&DaemonCommand::ReloadExtensions)?;
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
@@ -1,57 +1,1077 @@
+mod cli;
+mod placement;
 mod screen;
+mod theme;
 mod widget;
 
-use core::{Core, events::AppEvent};
+use clap::Parser;
+use cli::Command;
+use core::{
+    Core,
+    commands::{CommandResult, CommandType, normalize_shortcut},
+    config::{AppConfig, WindowConfig},
+    daemon::{Daemon, DaemonCommand},
+    events::{AppEvent, RecvError},
+    extensions::ExtensionStatus,
+    state::{AppMode, Section, SelectionMove},
+};
 
-use iced::Element;
-use log::error;
-use screen::{Screen, search::Search};
+use iced::{
+    Element, Event, Size, Subscription, Task, event,
+    futures::{StreamExt, future, stream},
+    keyboard::{self, Key, key::Named},
+    widget::{self as iced_widget, text_input},
+    window,
+};
+use log::{error, warn};
+use screen::{
+    Screen,
+    detail::DetailView,
+    form::{self, FormView},
+    logs::Logs,
+    search::{ActionPanel, Notice, Search},
+};
+use theme::Appearance;
+use widget::search_bar;
 
 fn main() -> iced::Result {
+    let command = match cli::Cli::parse().command {
+        None => DaemonCommand::Show { query: None },
+        Some(Command::Show { query }) => DaemonCommand::Show { query },
+        Some(Command::Toggle) => DaemonCommand::Toggle,
+        Some(Command::Quit) => DaemonCommand::Quit,
+        Some(command) => {
+            if let Err(e) = cli::run(command) {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    };
+
+    // Later invocations hand their command to the running instance
+    match core::daemon::send(&command) {
+        Ok(true) => return Ok(()),
+        Ok(false) if command == DaemonCommand::Quit => {
+            eprintln!("Cognito is not running");
+            std::process::exit(1);
+        }
+        Ok(false) => {}
+        Err(e) => eprintln!("Failed to reach the running instance: {e}"),
+    }
+    let daemon = match Daemon::bind() {
+        Ok(Some(daemon)) => Some(daemon),
+        // Another instance is starting, and takes the command once it listens
+        Ok(None) => {
+            if let Err(e) = send_when_listening(&command) {
+                eprintln!("Failed to reach the running instance: {e}");
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("Failed to open the control socket: {e}");
+            None
+        }
+    };
+    let query = match command {
+        DaemonCommand::Show { query } => query,
+        _ => None,
+    };
+
+    // The font can't change once the application runs, so it comes from the
+    // theme configured at startup
+    let config = AppConfig::load().unwrap_or_default();
+    let definition = core::theme::configured(&config.theme, prefers_dark(&config));
+    let size = Cognito::window_size(&config.window, definition.spacing, &Search::default());
+
     iced::application("Cognito", Cognito::update, Cognito::view)
+        .subscription(Cognito::subscription)
+        .theme(Cognito::theme)
+        .default_font(theme::font(&definition))
+        .settings(iced::Settings {
+            default_text_size: definition.font.size.into(),
+            ..Default::default()
+        })
+        .window_size(size)
+        .position(placement::position(&config.window, size))
         .resizable(false)
         .decorations(false)
-        .centered()
         .level(iced::window::Level::AlwaysOnTop)
         .exit_on_close_request(false)
-        .run()
+        .run_with(move || Cognito::new(daemon, query))
+}
+
+/// Messages of the UI: the events shared with the core, and those of
+/// screens with widget state that can't be part of an `AppEvent`.
+#[derive(Debug, Clone)]
+enum Message {
+    Event(AppEvent),
+    Form(form::Message),
 }
 
 struct Cognito {
     core: Core,
     screen: Screen,
+    appearance: Appearance,
+    /// The `[window]` section of the configuration.
+    window_config: WindowConfig,
+    /// Size the window was last given.
+    window_size: Size,
+    visible: bool,
+    /// The control socket, unless another instance owns it.
+    daemon: Option<Daemon>,
 }
 
-impl Default for Cognito {
-    fn default() -> Self {
+impl Cognito {
+    fn new(daemon: Option<Daemon>, query: Option<String>) -> (Self, Task<Message>) {
         let core = core::Core::new().unwrap();
-        let screen = Screen::Search(Search::default());
-        Self { core, screen }
+        if let Err(e) = core::logging::init(&core.context().get_config().logging) {
+            eprintln!("Failed to initialize logging: {e}");
+        }
+
+        let mut search = Box::<Search>::default();
+        if let Some(query) = query {
+            if let Err(e) = core.context().handle_query(query) {
+                error!("Query handle failed: {e}");
+            }
+            search.query = core.context().get_query();
+        }
+        let screen = Screen::Search(search);
+        let config = core.context().get_config();
+        let appearance = Appearance::new(&core::theme::configured(
+            &config.theme,
+            prefers_dark(&config),
+        ));
+
+        let window_size = Self::window_size(&config.window, appearance.spacing, &Search::default());
+
+        let mut starting = core.clone();
+        let start = Task::perform(async move { starting.start().await }, |result| {
+            if let Err(e) = result {
+                error!("Core failed to start: {e}");
+            }
+            AppEvent::ApplicationStarted
+        });
+
+        (
+            Self {
+                core,
+                screen,
+                appearance,
+                window_config: config.window,
+                window_size,
+                visible: true,
+                daemon,
+            },
+            start.map(Message::Event),
+        )
     }
-}
 
-impl Cognito {
-    pub fn update(&mut self, message: core::events::AppEvent) {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Event(event) => self.handle_event(event).map(Message::Event),
+            Message::Form(form::Message::Submit) => self.submit_form().map(Message::Event),
+            Message::Form(form::Message::Cancel) => self.pop().map(Message::Event),
+            Message::Form(message) => match &mut self.screen {
+                Screen::Form(form) => form.update(message).map(Message::Form),
+                _ => Task::none(),
+            },
+        }
+    }
+
+    fn handle_event(&mut self, message: core::events::AppEvent) -> Task<AppEvent> {
         match message {
             core::events::AppEvent::QueryChanged(query) => match &mut self.screen {
                 Screen::Search(search) => {
-                    if let Err(e) = self.core.context().handle_query(query) {
+                    if let Err(e) = self.core.context().handle_query(query.clone()) {
                         error!("Query handle failed: {e}");
                     }
+                    // Let extensions know, e.g. to prefetch results
+                    let _ = self
+                        .core
+                        .context()
+                        .event_bus
+                        .publish(AppEvent::QueryChanged(query.clone()));
 
+                    search.was_empty = search.query.is_empty();
                     search.query = self.core.context().get_query();
+                    search.hint = self.core.context().get_argument_hint(&search.query);
+                    search.arguments.clear();
+                    search.selected = self.core.context().get_selected();
+                    search.notice = None;
+                    return Task::batch([self.search(query), self.fit_window()]);
                 }
+                Screen::Logs(_) | Screen::Detail(_) | Screen::Form(_) => {}
             },
-            core::events::AppEvent::ResultsUpdated(items) => match &mut self.screen {
-                Screen::Search(search) => search.items = items,
+            core::events::AppEvent::ResultsUpdated(sections) => match &mut self.screen {
+                Screen::Search(search) => {
+                    let load = Self::load_results(&self.core, search, sections);
+                    search.faulted = self
+                        .core
+                        .extension_statuses()
+                        .into_iter()
+                        .filter_map(|(name, status)| match status {
+                            ExtensionStatus::Faulted(reason) => Some((name, reason)),
+                            _ => None,
+                        })
+                        .collect();
+                    let scroll = search.scroll_to_selection();
+                    return Task::batch([load, scroll, self.fit_window()]);
+                }
+                Screen::Logs(_) | Screen::Detail(_) | Screen::Form(_) => {}
+            },
+            core::events::AppEvent::ExpandSection(id) => {
+                if let Err(e) = self.core.context().expand_section(&id) {
+                    error!("Failed to expand section {id}: {e}");
+                }
+                if let Screen::Search(search) = &mut self.screen {
+                    let load =
+                        Self::load_results(&self.core, search, self.core.context().get_sections());
+                    return load.chain(self.fit_window());
+                }
+            }
+            core::events::AppEvent::MoveSelection(movement) => {
+                if let Screen::Search(search) = &mut self.screen {
+                    // Pages are as long as what fits on screen
+                    let movement = match movement {
+                        SelectionMove::PageUp(_) => SelectionMove::PageUp(search.page_size()),
+                        SelectionMove::PageDown(_) => SelectionMove::PageDown(search.page_size()),
+                        movement => movement,
+                    };
+                    if let Err(e) = self.core.context().move_selection(movement) {
+                        error!("Failed to move the selection: {e}");
+                    }
+                    search.selected = self.core.context().get_selected();
+                    search.actions = Self::action_panel(&self.core);
+                    // The preview may come or go with the selection
+                    let load = search.refresh_detail();
+                    let scroll = if search.actions.is_none() {
+                        search.scroll_to_selection()
+                    } else {
+                        Task::none()
+                    };
+                    return Task::batch([load, scroll, self.fit_window()]);
+                }
+            }
+            core::events::AppEvent::QuickSelect(index) => {
+                if let Screen::Search(search) = &mut self.screen
+                    && search.actions.is_none()
+                {
+                    if let Err(e) = self.core.context().select(index) {
+                        error!("Failed to select item {index}: {e}");
+                    }
+                    search.selected = self.core.context().get_selected();
+                    let load = search.refresh_detail().chain(self.fit_window());
+                    // Ctrl+9 with fewer items does nothing
+                    if self.core.context().get_selected() == index {
+                        return Task::batch([load, Task::done(AppEvent::ActivateSelected)]);
+                    }
+                    return load;
+                }
+            }
+            core::events::AppEvent::ActivateSelected
+                if matches!(self.screen, Screen::Search(_)) =>
+            {
+                if let Some((item_id, action_index)) = self.core.context().get_selected_action() {
+                    return Task::done(AppEvent::ItemActivated(item_id, action_index));
+                }
+                if let Some(item) = self.core.context().get_selected_item()
+                    && !item.actions.is_empty()
+                {
+                    // Arguments are typed before the item runs, unless they
+                    // were all typed after its title already
+                    if !item.arguments.is_empty() && self.core.context().get_arguments().is_none() {
+                        let open = self.open_arguments();
+                        if Self::missing_argument(&self.core).is_some() {
+                            return open;
+                        }
+                    }
+                    return Task::done(AppEvent::ItemActivated(item.id, 0));
+                }
+            }
+            core::events::AppEvent::ItemActivated(item_id, action_index) => {
+                let core = self.core.clone();
+                let activate = Task::perform(
+                    async move { core.activate(item_id, action_index).await },
+                    |result| {
+                        AppEvent::CommandCompleted(
+                            result.unwrap_or_else(|e| CommandResult::Error(e.to_string())),
+                        )
+                    },
+                );
+                return self.close_actions().chain(activate);
+            }
+            core::events::AppEvent::NextArgument { backward } => {
+                let focus = if backward {
+                    iced_widget::focus_previous()
+                } else {
+                    iced_widget::focus_next()
+                };
+                return match &self.screen {
+                    Screen::Form(_) => focus,
+                    Screen::Search(search) if search.actions.is_none() => {
+                        if !search.arguments.is_empty() {
+                            return focus;
+                        }
+                        if self
+                            .core
+                            .context()
+                            .get_selected_item()
+                            .is_some_and(|item| !item.arguments.is_empty())
+                        {
+                            return self.open_arguments();
+                        }
+                        Task::done(AppEvent::ToggleActions)
+                    }
+                    Screen::Search(_) => Task::done(AppEvent::ToggleActions),
+                    Screen::Logs(_) | Screen::Detail(_) => Task::none(),
+                };
+            }
+            core::events::AppEvent::ArgumentChanged(index, value) => {
+                if let Err(e) = self.core.context().set_argument(index, value) {
+                    error!("Failed to set argument {index}: {e}");
+                }
+                if let Screen::Search(search) = &mut self.screen {
+                    search.arguments = Self::argument_slots(&self.core);
+                }
+            }
+            core::events::AppEvent::ToggleActions => {
+                if let Screen::Search(search) = &self.screen {
+                    if search.actions.is_some() {
+                        return self.close_actions();
+                    }
+                    return self.open_actions();
+                }
+            }
+            core::events::AppEvent::ActionFilterChanged(filter) => {
+                if let Err(e) = self.core.context().set_action_filter(filter) {
+                    error!("Failed to filter actions: {e}");
+                }
+                if let Screen::Search(search) = &mut self.screen {
+                    search.actions = Self::action_panel(&self.core);
+                }
+            }
+            core::events::AppEvent::Shortcut(pressed) if matches!(self.screen, Screen::Form(_)) => {
+                // The actions of the results behind the form are out of reach
+                return match pressed.as_str() {
+                    "ctrl+enter" => self.submit_form(),
+                    _ => Task::none(),
+                };
+            }
+            core::events::AppEvent::Shortcut(pressed) => {
+                let state = self.core.context().state.read();
+                let item = state
+                    .as_ref()
+                    .ok()
+                    .and_then(|state| state.actions_item().or(state.selected_item()));
+                let action = item.and_then(|item| {
+                    let index = item
+                        .actions
+                        .iter()
+                        .position(|action| action.has_shortcut(&pressed));
+                    Some((item.id, index?))
+                });
+                drop(state);
+
+                if let Some((item_id, action_index)) = action {
+                    return Task::done(AppEvent::ItemActivated(item_id, action_index));
+                }
+            }
+            core::events::AppEvent::CommandCompleted(result) => match result {
+                CommandResult::Success => return self.hide(),
+                CommandResult::SetQuery(query) => {
+                    return Task::done(AppEvent::QueryChanged(query))
+                        .chain(text_input::move_cursor_to_end(search_bar::id()));
+                }
+                CommandResult::ShowResults(items) => {
+                    let core = self.core.clone();
+                    return Task::perform(
+                        async move { core.show_results(items).await },
+                        AppEvent::ResultsUpdated,
+                    );
+                }
+                CommandResult::PushList { title, items } => {
+                    let core = self.core.clone();
+                    return Task::perform(
+                        async move { core.push_list(title, items).await },
+                        |_| AppEvent::ViewChanged,
+                    )
+                    .chain(text_input::focus(search_bar::id()));
+                }
+                CommandResult::ShowDetail(item) => {
+                    return self.push_view(AppMode::Detail {
+                        item: Box::new(item),
+                    });
+                }
+                CommandResult::ShowForm(form) => {
+                    return self.push_view(AppMode::Form(Box::new(form)));
+                }
+                CommandResult::ShowExtensionLogs => {
+                    let extension = self
+                        .core
+                        .extension_logs()
+                        .into_iter()
+                        .next()
+                        .map(|(name, _)| name)
+                        .unwrap_or_default();
+                    return Task::done(AppEvent::ShowExtensionLogs(extension));
+                }
+                CommandResult::ShowNotification(message) => {
+                    if let Screen::Search(search) = &mut self.screen {
+                        search.notice = Some(Notice::Info(message));
+                    }
+                    return self.fit_window();
+                }
+                CommandResult::Error(message) => {
+                    error!("Action failed: {message}");
+                    if let Screen::Search(search) = &mut self.screen {
+                        search.notice = Some(Notice::Error(message));
+                    }
+                    return self.fit_window();
+                }
+            },
+            core::events::AppEvent::Dismiss => match &self.screen {
+                Screen::Search(search) if search.actions.is_some() => {
+                    return self.close_actions();
+                }
+                Screen::Search(search) if !search.arguments.is_empty() => {
+                    return self.close_arguments();
+                }
+                Screen::Search(search) if search.breadcrumbs.len() > 1 => return self.pop(),
+                Screen::Search(search) if !search.query.is_empty() => {
+                    return Task::done(AppEvent::QueryChanged(String::new()))
+                        .chain(text_input::focus(search_bar::id()));
+                }
+                Screen::Search(_) => return self.hide(),
+                Screen::Logs(_) => return Task::done(AppEvent::ExitToSearch),
+                Screen::Detail(_) | Screen::Form(_) => return self.pop(),
+            },
+            core::events::AppEvent::NavigateBack { captured } => match &self.screen {
+                // Backspace also reaches the search bar first, so the query
+                // must have been empty before it, not just emptied by it
+                Screen::Search(search)
+                    if search.actions.is_none()
+                        && search.query.is_empty()
+                        && search.was_empty
+                        && search.breadcrumbs.len() > 1 =>
+                {
+                    return self.pop();
+                }
+                // Fields of a form take Backspace while they are typed in
+                Screen::Detail(_) => return self.pop(),
+                Screen::Form(_) if !captured => return self.pop(),
+                Screen::Search(_) | Screen::Logs(_) | Screen::Form(_) => {}
+            },
+            core::events::AppEvent::ViewChanged => return self.show_view(),
+            core::events::AppEvent::ColorSchemeChanged { dark } => {
+                let config = self.core.context().get_config();
+                self.appearance = Appearance::new(&core::theme::configured(&config.theme, dark));
+            }
+            core::events::AppEvent::OpenLink(url) => {
+                return self.execute(CommandType::OpenUrl { path: url });
+            }
+            core::events::AppEvent::ResultsScrolled { offset, height } => {
+                if let Screen::Search(search) = &mut self.screen {
+                    search.scroll_offset = offset;
+                    search.viewport_height = height;
+                }
+            }
+            core::events::AppEvent::ShowExtensionLogs(extension) => {
+                self.screen = Screen::Logs(Logs {
+                    extension,
+                    extensions: self.core.extension_logs(),
+                });
+                return self.fit_window();
+            }
+            core::events::AppEvent::PreviewLoaded { item_id, preview } => match &mut self.screen {
+                Screen::Search(search) => {
+                    if let Some(detail) = &mut search.detail {
+                        detail.loaded(item_id, preview);
+                    }
+                }
+                Screen::Detail(detail) => detail.loaded(item_id, preview),
+                Screen::Logs(_) | Screen::Form(_) => {}
+            },
+            core::events::AppEvent::ExitToSearch => {
+                if let Err(e) = self.core.context().pop_to_root() {
+                    error!("Failed to return to the search: {e}");
+                }
+                return self.show_view();
+            }
+            core::events::AppEvent::ShowLauncher { query } => return self.show(query),
+            core::events::AppEvent::HideLauncher => return self.hide(),
+            core::events::AppEvent::ToggleLauncher if self.visible => return self.hide(),
+            core::events::AppEvent::ToggleLauncher => return self.show(None),
+            // File dialogs opened by forms take the focus too
+            core::events::AppEvent::FocusLost
+                if self.window_config.hide_on_focus_loss
+                    && !matches!(self.screen, Screen::Form(_)) =>
+            {
+                return self.hide();
+            }
+            core::events::AppEvent::ReloadExtensions => {
+                let core = self.core.clone();
+                let query = self.core.context().get_query();
+                return Task::perform(
+                    async move {
+                        if let Err(e) = core.reload_extensions().await {
+                            error!("Failed to reload extensions: {e}");
+                        }
+                        core.search(query).await
+                    },
+                    AppEvent::ResultsUpdated,
+                );
+            }
+            core::events::AppEvent::Quit => {
+                let _ = self
+                    .core
+                    .context()
+                    .event_bus
+                    .publish(AppEvent::ApplicationClosing);
+                return iced::exit();
+            }
+            core::events::AppEvent::ApplicationStarted => {
+                return self.search(self.core.context().get_query());
+            }
+            core::events::AppEvent::ExtensionItemsChanged(_) => match &self.screen {
+                // Background items are shown whenever they match the query
+                Screen::Search(search) => return self.search(search.query.clone()),
+                Screen::Logs(_) | Screen::Detail(_) | Screen::Form(_) => {}
             },
             _ => {}
         }
+
+        Task::none()
+    }
+
+    /// Events published on the core's event bus that the UI reacts to.
+    fn subscription(&self) -> Subscription<Message> {
+        // iced keeps the first stream for each id, so subscribing is left
+        // to when it's started instead of done on every update
+        let event_bus = self.core.context().event_bus.clone();
+        let events = stream::once(async move { event_bus.subscribe() })
+            .flat_map(|receiver| {
+                stream::unfold(receiver, |mut receiver| async move {
+                    loop {
+                        match receiver.recv().await {
+                            Ok(event) => return Some((event, receiver)),
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => return None,
+                        }
+                    }
+                })
+            })
+            .filter(|event| future::ready(matches!(event, AppEvent::ExtensionItemsChanged(_))));
+
+        let mut subscriptions = vec![
+            Subscription::run_with_id("core-events", events),
+            event::listen_with(keyboard_shortcut),
+            event::listen_with(window_event),
+        ];
+        if self.core.context().get_config().theme.follow_system {
+            let changes = stream::once(core::theme::color_scheme_changes())
+                .filter_map(|changes| {
+                    future::ready(
+                        changes
+                            .inspect_err(|e| warn!("Failed to follow the color scheme: {e}"))
+                            .ok(),
+                    )
+                })
+                .flatten()
+                .map(|dark| AppEvent::ColorSchemeChanged { dark });
+            subscriptions.push(Subscription::run_with_id("color-scheme", changes));
+        }
+        if let Some(daemon) = &self.daemon {
+            subscriptions.push(Subscription::run_with_id(
+                "daemon",
+                daemon.commands().map(AppEvent::from),
+            ));
+        }
+
+        Subscription::batch(subscriptions).map(Message::Event)
+    }
+
+    fn theme(&self) -> iced::Theme {
+        self.appearance.theme.clone()
+    }
+
+    /// Size of the window showing `search`.
+    fn window_size(config: &WindowConfig, spacing: u16, search: &Search) -> Size {
+        let fit = config.grow_with_results && search.actions.is_none();
+        let height = search.height(spacing, config.max_rows, fit);
+        Size::new(config.width.into(), height)
+    }
+
+    /// Resizes the window to fit the results. Other screens get the room
+    /// all rows of results would take. Called whenever the screen, or what
+    /// the search screen shows around the results, changes.
+    fn fit_window(&mut self) -> Task<AppEvent> {
+        let mut config = self.window_config.clone();
+        let size = match &self.screen {
+            Screen::Search(search) => Self::window_size(&config, self.appearance.spacing, search),
+            Screen::Logs(_) | Screen::Detail(_) | Screen::Form(_) => {
+                config.grow_with_results = false;
+                Self::window_size(&config, self.appearance.spacing, &Search::default())
+            }
+        };
+        if size == self.window_size {
+            return Task::none();
+        }
+
+        self.window_size = size;
+        window::get_latest().and_then(move |id| window::resize(id, size))
+    }
+
+    /// Switches to the search screen, showing the current view from the state.
+    fn show_search(&mut self) -> Task<AppEvent> {
+        let query = self.core.context().get_query();
+        let mut search = Search {
+            was_empty: query.is_empty(),
+            query,
+            ..Default::default()
+        };
+        let load = Self::load_results(&self.core, &mut search, self.core.context().get_sections());
+        self.screen = Screen::Search(Box::new(search));
+        load
+    }
+
+    /// Validates the open form, and sends its values to its submit command
+    /// if they are valid.
+    fn submit_form(&mut self) -> Task<AppEvent> {
+        let Screen::Form(form) = &mut self.screen else {
+            return Task::none();
+        };
+        form.errors = form.form.validate(&form.values);
+        if !form.errors.is_empty() {
+            return Task::none();
+        }
+
+        let submission = form.form.submission(form.values.clone());
+        // The result of the submit command is shown on the view below
+        let back = self.pop();
+        match submission {
+            Ok(command) => back.chain(self.execute(command)),
+            Err(e) => back.chain(Task::done(AppEvent::CommandCompleted(
+                CommandResult::Error(e.to_string()),
+            ))),
+        }
+    }
+
+    /// Opens `mode` on top of the current view.
+    fn push_view(&mut self, mode: AppMode) -> Task<AppEvent> {
+        if let Err(e) = self.core.context().push_view(mode) {
+            error!("Failed to open the view: {e}");
+            return Task::none();
+        }
+        self.show_view()
+    }
+
+    /// Switches to the screen of the view on top of the stack, e.g. after a
+    /// view was opened or closed.
+    fn show_view(&mut self) -> Task<AppEvent> {
+        let breadcrumbs = self.core.context().get_breadcrumbs();
+        let show = match self.core.context().get_mode() {
+            AppMode::Detail { item } => {
+                let (detail, load) = DetailView::new(&item, breadcrumbs);
+                self.screen = Screen::Detail(Box::new(detail));
+                load
+            }
+            AppMode::Form(form) => {
+                let form = FormView::new(*form, breadcrumbs);
+                let focus = form.focus_first();
+                self.screen = Screen::Form(Box::new(form));
+                focus
+            }
+            AppMode::Search | AppMode::List { .. } | AppMode::Actions { .. } => {
+                if let Screen::Search(search) = &mut self.screen {
+                    search.query = self.core.context().get_query();
+                    search.was_empty = search.query.is_empty();
+                    let load =
+                        Self::load_results(&self.core, search, self.core.context().get_sections());
+                    Task::batch([load, search.scroll_to_selection()])
+                } else {
+                    Task::batch([self.show_search(), text_input::focus(search_bar::id())])
+                }
+            }
+        };
+        Task::batch([show, self.fit_window()])
+    }
+
+    fn execute(&self, command: CommandType) -> Task<AppEvent> {
+        let core = self.core.clone();
+        Task::perform(
+            async move { core.execute_command(command).await },
+            |result| {
+                AppEvent::CommandCompleted(
+                    result.unwrap_or_else(|e| CommandResult::Error(e.to_string())),
+                )
+            },
+        )
+    }
+
+    /// Shows `sections` with their icons and the selection from the state.
+    /// Returns the task reading the file of the selected item's preview.
+    fn load_results(core: &Core, search: &mut Search, sections: Vec<Section>) -> Task<AppEvent> {
+        search.icons = core.context().icons.cached_for(&sections);
+        search.icon_size = core.context().get_config().icons.size;
+        search.sections = sections;
+        search.selected = core.context().get_selected();
+        search.actions = Self::action_panel(core);
+        search.breadcrumbs = core.context().get_breadcrumbs();
+        search.arguments = Self::argument_slots(core);
+
+        let preview = core.context().get_config().preview;
+        search.preview_width = preview.enabled.then_some(preview.width);
+        search.refresh_detail()
+    }
+
+    /// Returns to the previous view.
+    fn pop(&mut self) -> Task<AppEvent> {
+        match self.core.context().pop() {
+            Ok(true) => Task::done(AppEvent::ViewChanged),
+            Ok(false) => Task::none(),
+            Err(e) => {
+                error!("Failed to return to the previous view: {e}");
+                Task::none()
+            }
+        }
     }
 
-    pub fn view(&self) -> Element<'_, AppEvent> {
+    /// The open action panel, from the state.
+    fn action_panel(core: &Core) -> Option<Box<ActionPanel>> {
+        let state = core.context().state.read().ok()?;
+        let item = state.actions_item()?;
+        let AppMode::Actions {
+            item_id,
+            filter,
+            selected,
+        } = &state.mode
+        else {
+            return None;
+        };
+
+        Some(Box::new(ActionPanel {
+            item_id: *item_id,
+            item_title: item.title.clone(),
+            filter: filter.clone(),
+            actions: state
+                .filtered_actions()
+                .into_iter()
+                .map(|(index, action)| (index, action.clone()))
+                .collect(),
+            selected: *selected,
+        }))
+    }
+
+    /// Placeholders and values of the arguments being typed, from the state.
+    fn argument_slots(core: &Core) -> Vec<(String, String)> {
+        let Some((arguments, values)) = core.context().get_arguments() else {
+            return Vec::new();
+        };
+        arguments
+            .iter()
+            .map(|argument| argument.placeholder().to_string())
+            .zip(values)
+            .collect()
+    }
+
+    /// Index of the first required argument without a value.
+    fn missing_argument(core: &Core) -> Option<usize> {
+        let (arguments, values) = core.context().get_arguments()?;
+        arguments
+            .iter()
+            .zip(&values)
+            .position(|(argument, value)| argument.required && value.trim().is_empty())
+    }
+
+    /// Starts typing the arguments of the selected item, in the first one
+    /// still missing.
+    fn open_arguments(&mut self) -> Task<AppEvent> {
+        match self.core.context().open_arguments() {
+            Ok(true) => {}
+            Ok(false) => return Task::none(),
+            Err(e) => {
+                error!("Failed to open the arguments: {e}");
+                return Task::none();
+            }
+        }
+
+        if let Screen::Search(search) = &mut self.screen {
+            search.arguments = Self::argument_slots(&self.core);
+        }
+        let index = Self::missing_argument(&self.core).unwrap_or_default();
+        text_input::focus(search_bar::argument_id(index))
+    }
+
+    fn close_arguments(&mut self) -> Task<AppEvent> {
+        if let Err(e) = self.core.context().close_arguments() {
+            error!("Failed to close the arguments: {e}");
+        }
+        if let Screen::Search(search) = &mut self.screen {
+            search.arguments.clear();
+        }
+        text_input::focus(search_bar::id())
+    }
+
+    fn open_actions(&mut self) -> Task<AppEvent> {
+        match self.core.context().open_actions() {
+            Ok(true) => {}
+            Ok(false) => return Task::none(),
+            Err(e) => {
+                error!("Failed to open the action panel: {e}");
+                return Task::none();
+            }
+        }
+
+        if let Screen::Search(search) = &mut self.screen {
+            search.actions = Self::action_panel(&self.core);
+        }
+        // The panel takes the room of all rows of results
+        Task::batch([
+            text_input::focus(Search::action_filter_id()),
+            self.fit_window(),
+        ])
+    }
+
+    /// Closes the action panel, if it is open.
+    fn close_actions(&mut self) -> Task<AppEvent> {
+        let Screen::Search(search) = &mut self.screen else {
+            return Task::none();
+        };
+        if search.actions.take().is_none() {
+            return Task::none();
+        }
+
+        if let Err(e) = self.core.context().close_actions() {
+            error!("Failed to close the action panel: {e}");
+        }
+        Task::batch([text_input::focus(search_bar::id()), self.fit_window()])
+    }
+
+    /// Shows the launcher on the configured monitor, with the search bar
+    /// focused. Searches for `query` when given, otherwise starts from an
+    /// empty search unless `keep_query` is set.
+    fn show(&mut self, query: Option<String>) -> Task<AppEvent> {
+        let config = self.window_config.clone();
+        let focus = window::get_latest()
+            .and_then(window::gain_focus)
+            .chain(text_input::focus(search_bar::id()));
+        let reset = match query {
+            Some(query) => self.reset(query),
+            None if self.visible || config.keep_query => Task::none(),
+            None => self.reset(String::new()),
+        };
+        if self.visible {
+            return reset.chain(focus);
+        }
+
+        self.visible = true;
+        let _ = self
+            .core
+            .context()
+            .event_bus
+            .publish(AppEvent::LauncherShown);
+
+        let place = match placement::position(&config, self.window_size) {
+            window::Position::Specific(position) => {
+                window::get_latest().and_then(move |id| window::move_to(id, position))
+            }
+            _ => Task::none(),
+        };
+
+        reset
+            .chain(place)
+            .chain(
+                window::get_latest().and_then(|id| window::change_mode(id, window::Mode::Windowed)),
+            )
+            .chain(focus)
+    }
+
+    /// Returns to the root search, searching for `query`.
+    fn reset(&mut self, query: String) -> Task<AppEvent> {
+        if let Err(e) = self.core.context().pop_to_root() {
+            error!("Failed to return to the search: {e}");
+        }
+        let load = self.show_search();
+        Task::batch([load, Task::done(AppEvent::QueryChanged(query))])
+    }
+
+    fn hide(&mut self) -> Task<AppEvent> {
+        if !self.visible {
+            return Task::none();
+        }
+
+        self.visible = false;
+        let _ = self
+            .core
+            .context()
+            .event_bus
+            .publish(AppEvent::LauncherHidden);
+        window::get_latest().and_then(|id| window::change_mode(id, window::Mode::Hidden))
+    }
+
+    fn search(&self, query: String) -> Task<AppEvent> {
+        let core = self.core.clone();
+        Task::perform(
+            async move { core.search(query).await },
+            AppEvent::ResultsUpdated,
+        )
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
         match &self.screen {
-            Screen::Search(search) => search.view(),
+            Screen::Search(search) => search.view(&self.appearance).map(Message::Event),
+            Screen::Logs(logs) => logs.view().map(Message::Event),
+            Screen::Detail(detail) => detail
+                .view(self.window_size.width as u16, &self.appearance)
+                .map(Message::Event),
+            Screen::Form(form) => form.view(&self.appearance).map(Message::Form),
         }
     }
 }
+
+/// Sends `command` to an instance that is still starting, waiting a few
+/// seconds at most for it to listen on the control socket.
+fn send_when_listening(command: &DaemonCommand) -> anyhow::Result<()> {
+    for _ in 0..50 {
+        if core::daemon::send(command)? {
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    anyhow::bail!("The running instance doesn't answer")
+}
+
+/// The desktop's color preference, when the theme follows it.
+fn prefers_dark(config: &AppConfig) -> Option<bool> {
+    config
+        .theme
+        .follow_system
+        .then(core::theme::system_prefers_dark)
+        .flatten()
+}
+
+/// Keys that work regardless of the focused widget. Home and End also move
+/// the cursor of the search bar, which is harmless.
+fn keyboard_shortcut(event: Event, status: event::Status, _window: window::Id) -> Option<AppEvent> {
+    let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = event else {
+        return None;
+    };
+
+    let event = match key.as_ref() {
+        Key::Named(Named::ArrowUp) => AppEvent::MoveSelection(SelectionMove::Up),
+        Key::Named(Named::ArrowDown) => AppEvent::MoveSelection(SelectionMove::Down),
+        // The page size is filled in by the search screen
+        Key::Named(Named::PageUp) => AppEvent::MoveSelection(SelectionMove::PageUp(0)),
+        Key::Named(Named::PageDown) => AppEvent::MoveSelection(SelectionMove::PageDown(0)),
+        Key::Named(Named::Home) => AppEvent::MoveSelection(SelectionMove::First),
+        Key::Named(Named::End) => AppEvent::MoveSelection(SelectionMove::Last),
+        // Enter with modifiers runs the action with that shortcut, see below
+        Key::Named(Named::Enter) if modifiers.is_empty() => AppEvent::ActivateSelected,
+        Key::Named(Named::Escape) => AppEvent::Dismiss,
+        Key::Named(Named::Tab) => AppEvent::NextArgument {
+            backward: modifiers.shift(),
+        },
+        Key::Named(Named::Backspace) if modifiers.is_empty() => AppEvent::NavigateBack {
+            captured: status == event::Status::Captured,
+        },
+        Key::Character("k") if modifiers.control() => AppEvent::ToggleActions,
+        Key::Character(character) if modifiers.control() => match character.parse::<usize>() {
+            Ok(number @ 1..=9) => AppEvent::QuickSelect(number - 1),
+            _ => return shortcut(&key, modifiers).map(AppEvent::Shortcut),
+        },
+        _ => return shortcut(&key, modifiers).map(AppEvent::Shortcut),
+    };
+
+    Some(event)
+}
+
+/// Window events that show or hide the launcher.
+fn window_event(event: Event, _status: event::Status, _window: window::Id) -> Option<AppEvent> {
+    match event {
+        Event::Window(window::Event::Unfocused) => Some(AppEvent::FocusLost),
+        // Closing the window only hides the launcher, see `Quit`
+        Event::Window(window::Event::CloseRequested) => Some(AppEvent::HideLauncher),
+        _ => None,
+    }
+}
+
+/// `key` with `modifiers` as action shortcuts are written, e.g.
+/// `"ctrl+shift+c"`. Keys without Ctrl, Alt or Super are typing, not
+/// shortcuts.
+fn shortcut(key: &Key, modifiers: keyboard::Modifiers) -> Option<String> {
+    if !(modifiers.control() || modifiers.alt() || modifiers.logo()) {
+        return None;
+    }
+
+    let key = match key.as_ref() {
+        Key::Character(character) => character.to_string(),
+        Key::Named(Named::Enter) => "enter".to_string(),
+        Key::Named(Named::Space) => "space".to_string(),
+        Key::Named(Named::Backspace) => "backspace".to_string(),
+        Key::Named(Named::Delete) => "delete".to_string(),
+        _ => return None,
+    };
+    let modifiers = [
+        (modifiers.control(), "ctrl"),
+        (modifiers.alt(), "alt"),
+        (modifiers.shift(), "shift"),
+        (modifiers.logo(), "super"),
+    ];
+    let mut parts: Vec<_> = modifiers
+        .into_iter()
+        .filter(|(pressed, _)| *pressed)
+        .map(|(_, name)| name.to_string())
+        .collect();
+    parts.push(key);
+
+    Some(normalize_shortcut(&parts.join("+")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keyboard::{Location, Modifiers, key};
+
+    fn press(key: Key, modifiers: Modifiers) -> Option<AppEvent> {
+        let event = Event::Keyboard(keyboard::Event::KeyPressed {
+            modified_key: key.clone(),
+            key,
+            physical_key: key::Physical::Unidentified(key::NativeCode::Unidentified),
+            location: Location::Standard,
+            modifiers,
+            text: None,
+        });
+        keyboard_shortcut(event, event::Status::Ignored, window::Id::unique())
+    }
+
+    #[test]
+    fn test_keyboard_shortcut() {
+        let enter = || Key::Named(Named::Enter);
+        assert_eq!(
+            press(enter(), Modifiers::empty()),
+            Some(AppEvent::ActivateSelected)
+        );
+        assert_eq!(
+            press(enter(), Modifiers::CTRL),
+            Some(AppEvent::Shortcut("ctrl+enter".to_string()))
+        );
+        assert_eq!(
+            press(Key::Character("3".into()), Modifiers::CTRL),
+            Some(AppEvent::QuickSelect(2))
+        );
+        assert_eq!(
+            press(
+                Key::Character("c".into()),
+                Modifiers::CTRL | Modifiers::SHIFT
+            ),
+            Some(AppEvent::Shortcut("ctrl+shift+c".to_string()))
+        );
+        assert_eq!(
+            press(Key::Named(Named::Backspace), Modifiers::empty()),
+            Some(AppEvent::NavigateBack { captured: false })
+        );
+        assert_eq!(press(Key::Character("c".into()), Modifiers::empty()), None);
+    }
+}
@@ -0,0 +1,99 @@
+use core::config::{MonitorChoice, WindowConfig};
+
+use iced::{Point, Rectangle, Size, window::Position};
+use log::warn;
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        randr::ConnectionExt as _,
+        xproto::{AtomEnum, ConnectionExt as _},
+    },
+};
+
+/// Where the window of `size` opens. Wayland compositors place windows
+/// themselves, and so does any platform where the monitor can't be found;
+/// the window is centered there.
+pub fn position(config: &WindowConfig, size: Size) -> Position {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        return Position::Centered;
+    }
+
+    match monitor(config.monitor) {
+        Ok(Some(monitor)) => Position::Specific(Point::new(
+            monitor.x + (monitor.width - size.width) / 2.0,
+            monitor.y + monitor.height * config.vertical_offset.clamp(0.0, 1.0),
+        )),
+        Ok(None) => Position::Centered,
+        Err(e) => {
+            warn!("Failed to find the {:?} monitor: {e}", config.monitor);
+            Position::Centered
+        }
+    }
+}
+
+/// Bounds of the chosen monitor, from the X server. Falls back to the
+/// primary monitor when there is no cursor or focused window to go by.
+fn monitor(choice: MonitorChoice) -> anyhow::Result<Option<Rectangle>> {
+    let (connection, screen) = x11rb::connect(None)?;
+    let root = connection.setup().roots[screen].root;
+
+    let monitors = connection
+        .randr_get_monitors(root, true)?
+        .reply()?
+        .monitors
+        .into_iter()
+        .map(|monitor| {
+            let bounds = Rectangle::new(
+                Point::new(monitor.x.into(), monitor.y.into()),
+                Size::new(monitor.width.into(), monitor.height.into()),
+            );
+            (monitor.primary, bounds)
+        })
+        .collect::<Vec<_>>();
+
+    let point = match choice {
+        MonitorChoice::Primary => None,
+        MonitorChoice::Cursor => {
+            let pointer = connection.query_pointer(root)?.reply()?;
+            Some(Point::new(pointer.root_x.into(), pointer.root_y.into()))
+        }
+        MonitorChoice::Focused => {
+            let active = connection
+                .intern_atom(false, b"_NET_ACTIVE_WINDOW")?
+                .reply()?
+                .atom;
+            let property = connection
+                .get_property(false, root, active, AtomEnum::WINDOW, 0, 1)?
+                .reply()?;
+            match property.value32().and_then(|mut value| value.next()) {
+                Some(window) if window != x11rb::NONE => {
+                    let geometry = connection.get_geometry(window)?.reply()?;
+                    let origin = connection
+                        .translate_coordinates(window, root, 0, 0)?
+                        .reply()?;
+                    Some(Point::new(
+                        f32::from(origin.dst_x) + f32::from(geometry.width) / 2.0,
+                        f32::from(origin.dst_y) + f32::from(geometry.height) / 2.0,
+                    ))
+                }
+                _ => None,
+            }
+        }
+    };
+
+    let chosen = point.and_then(|point| {
+        monitors
+            .iter()
+            .find(|(_, bounds)| bounds.contains(point))
+            .map(|(_, bounds)| *bounds)
+    });
+    let primary = || {
+        monitors
+            .iter()
+            .find(|(primary, _)| *primary)
+            .or(monitors.first())
+            .map(|(_, bounds)| *bounds)
+    };
+
+    Ok(chosen.or_else(primary))
+}
@@ -0,0 +1,57 @@
+use core::{
+    events::AppEvent,
+    state::{Item, Preview},
+};
+
+use iced::{
+    Element, Task,
+    widget::{column, container, text},
+};
+use uuid::Uuid;
+
+use crate::{theme::Appearance, widget::detail::Detail};
+
+/// The preview of an item on a view of its own, see
+/// `CommandResult::ShowDetail`.
+pub struct DetailView {
+    /// Titles of the views down to this one, see `AppState::breadcrumbs`.
+    pub breadcrumbs: Vec<String>,
+    detail: Option<Detail>,
+}
+
+impl DetailView {
+    /// The view of `item`, with the task reading the file it shows.
+    pub fn new(item: &Item, breadcrumbs: Vec<String>) -> (Self, Task<AppEvent>) {
+        let view = Self {
+            breadcrumbs,
+            detail: Detail::new(item),
+        };
+        (view, Detail::load(item))
+    }
+
+    /// See `Detail::loaded`.
+    pub fn loaded(&mut self, item_id: Uuid, preview: Result<Preview, String>) {
+        if let Some(detail) = &mut self.detail {
+            detail.loaded(item_id, preview);
+        }
+    }
+
+    pub fn view<'a>(&'a self, width: u16, appearance: &'a Appearance) -> Element<'a, AppEvent> {
+        let body = match &self.detail {
+            Some(detail) => detail.view(width.saturating_sub(2 * appearance.spacing), appearance),
+            None => text("Nothing to preview").style(text::secondary).into(),
+        };
+
+        container(
+            column![
+                text(self.breadcrumbs.join(" › "))
+                    .size(12)
+                    .style(text::secondary),
+                body,
+            ]
+            .spacing(appearance.spacing),
+        )
+        .padding(appearance.spacing)
+        .into()
+    }
+}
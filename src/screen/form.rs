@@ -0,0 +1,227 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use core::forms::{Field, FieldKind, Form, FormValues};
+
+use iced::{
+    Element, Length, Task,
+    widget::{
+        button, checkbox, column, container, horizontal_space, pick_list, row, scrollable, text,
+        text_editor, text_input,
+    },
+};
+use serde_json::Value;
+
+use crate::theme::Appearance;
+
+/// Messages of the form screen. Text areas keep editor state that can't be
+/// part of an `AppEvent`, so the form has messages of its own.
+#[derive(Debug, Clone)]
+pub enum Message {
+    Changed(String, Value),
+    Edited(String, text_editor::Action),
+    PickFile(String),
+    FilePicked(String, Option<PathBuf>),
+    Submit,
+    Cancel,
+}
+
+/// A form asked for by an action, see `CommandResult::ShowForm`.
+pub struct FormView {
+    pub form: Form,
+    /// Titles of the views down to this one, see `AppState::breadcrumbs`.
+    pub breadcrumbs: Vec<String>,
+    pub values: FormValues,
+    /// Problems found when submitting, by field id.
+    pub errors: HashMap<String, String>,
+    editors: HashMap<String, text_editor::Content>,
+}
+
+impl FormView {
+    pub fn new(form: Form, breadcrumbs: Vec<String>) -> Self {
+        let values = form.initial_values();
+        let editors = form
+            .fields
+            .iter()
+            .filter_map(|field| match &field.kind {
+                FieldKind::Textarea { default } => {
+                    Some((field.id.clone(), text_editor::Content::with_text(default)))
+                }
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            form,
+            breadcrumbs,
+            values,
+            errors: HashMap::new(),
+            editors,
+        }
+    }
+
+    fn input_id(field: &Field) -> text_input::Id {
+        text_input::Id::new(format!("form-{}", field.id))
+    }
+
+    /// Focuses the first field typed into, if there is one.
+    pub fn focus_first<T>(&self) -> Task<T> {
+        self.form
+            .fields
+            .iter()
+            .find(|field| matches!(field.kind, FieldKind::Text { .. } | FieldKind::Date { .. }))
+            .map(|field| text_input::focus(Self::input_id(field)))
+            .unwrap_or_else(Task::none)
+    }
+
+    /// Applies the messages that only change the form itself.
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Changed(id, value) => {
+                self.errors.remove(&id);
+                self.values.insert(id, value);
+            }
+            Message::Edited(id, action) => {
+                let Some(content) = self.editors.get_mut(&id) else {
+                    return Task::none();
+                };
+                let edited = action.is_edit();
+                content.perform(action);
+                if edited {
+                    self.errors.remove(&id);
+                    self.values.insert(id, Value::String(content.text()));
+                }
+            }
+            Message::PickFile(id) => {
+                let directory = self.form.fields.iter().any(|field| {
+                    field.id == id && matches!(field.kind, FieldKind::File { directory: true })
+                });
+                return Task::perform(
+                    async move {
+                        let dialog = rfd::AsyncFileDialog::new();
+                        let picked = if directory {
+                            dialog.pick_folder().await
+                        } else {
+                            dialog.pick_file().await
+                        };
+                        picked.map(|handle| handle.path().to_path_buf())
+                    },
+                    move |path| Message::FilePicked(id.clone(), path),
+                );
+            }
+            Message::FilePicked(id, Some(path)) => {
+                let path = path.to_string_lossy().into_owned();
+                return Task::done(Message::Changed(id, Value::String(path)));
+            }
+            Message::FilePicked(_, None) | Message::Submit | Message::Cancel => {}
+        }
+
+        Task::none()
+    }
+
+    pub fn view<'a>(&'a self, appearance: &'a Appearance) -> Element<'a, Message> {
+        let fields = self
+            .form
+            .fields
+            .iter()
+            .map(|field| self.render_field(field))
+            .collect::<Vec<_>>();
+
+        let buttons = row![
+            horizontal_space(),
+            button(text("Cancel"))
+                .style(button::secondary)
+                .on_press(Message::Cancel),
+            button(text(self.form.submit_label.to_owned())).on_press(Message::Submit),
+        ]
+        .spacing(5);
+
+        container(
+            column![
+                text(self.breadcrumbs.join(" › "))
+                    .size(12)
+                    .style(text::secondary),
+                text(self.form.title.to_owned()).size(18),
+                scrollable(column(fields).spacing(12)).height(Length::Fill),
+                buttons,
+            ]
+            .spacing(appearance.spacing),
+        )
+        .padding(appearance.spacing)
+        .into()
+    }
+
+    fn render_field<'a>(&'a self, field: &'a Field) -> Element<'a, Message> {
+        let value = self.values.get(&field.id);
+        let text_value = value.and_then(Value::as_str).unwrap_or_default();
+        let placeholder = field.placeholder.as_deref().unwrap_or_default();
+        let id = field.id.clone();
+
+        let input: Element<'_, Message> = match &field.kind {
+            FieldKind::Text { .. } => text_input(placeholder, text_value)
+                .id(Self::input_id(field))
+                .on_input(move |text| Message::Changed(id.clone(), Value::String(text)))
+                .on_submit(Message::Submit)
+                .into(),
+            FieldKind::Date { .. } => {
+                let placeholder = field.placeholder.as_deref().unwrap_or("YYYY-MM-DD");
+                text_input(placeholder, text_value)
+                    .id(Self::input_id(field))
+                    .on_input(move |text| Message::Changed(id.clone(), Value::String(text)))
+                    .on_submit(Message::Submit)
+                    .into()
+            }
+            FieldKind::Textarea { .. } => match self.editors.get(&field.id) {
+                Some(content) => text_editor(content)
+                    .placeholder(placeholder)
+                    .height(100)
+                    .on_action(move |action| Message::Edited(id.clone(), action))
+                    .into(),
+                None => horizontal_space().into(),
+            },
+            FieldKind::Dropdown { options, .. } => {
+                let selected = options.iter().find(|option| option.value == text_value);
+                pick_list(options.as_slice(), selected.cloned(), move |option| {
+                    Message::Changed(id.clone(), Value::String(option.value))
+                })
+                .placeholder(placeholder)
+                .width(Length::Fill)
+                .into()
+            }
+            FieldKind::Checkbox { .. } => {
+                let checked = value.and_then(Value::as_bool).unwrap_or_default();
+                checkbox(field.label.to_owned(), checked)
+                    .on_toggle(move |checked| Message::Changed(id.clone(), Value::Bool(checked)))
+                    .into()
+            }
+            FieldKind::File { .. } => {
+                let path = if text_value.is_empty() {
+                    text("No file chosen").style(text::secondary)
+                } else {
+                    text(text_value.to_owned())
+                };
+                row![
+                    button(text("Choose...")).on_press(Message::PickFile(id)),
+                    path
+                ]
+                .spacing(10)
+                .into()
+            }
+        };
+
+        // Checkboxes carry their label themselves
+        let label = (!matches!(field.kind, FieldKind::Checkbox { .. })).then(|| {
+            let required = if field.required { " *" } else { "" };
+            text(format!("{}{required}", field.label)).size(13)
+        });
+        let error = self
+            .errors
+            .get(&field.id)
+            .map(|error| text(error.to_owned()).size(12).style(text::danger));
+
+        column(label.map(Element::from))
+            .push(input)
+            .push_maybe(error)
+            .spacing(4)
+            .into()
+    }
+}
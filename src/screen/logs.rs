@@ -0,0 +1,82 @@
+use core::{events::AppEvent, extensions::LogEntry};
+
+use iced::{
+    Color, Element, Length,
+    widget::{button, column, container, row, scrollable, text},
+};
+
+/// Recent log output of the loaded extensions, one extension at a time.
+#[derive(Default, Clone)]
+pub struct Logs {
+    pub extension: String,
+    pub extensions: Vec<(String, Vec<LogEntry>)>,
+}
+
+impl Logs {
+    pub fn view(&self) -> Element<'_, AppEvent> {
+        let tabs = row(self
+            .extensions
+            .iter()
+            .map(|(name, _)| {
+                let tab = button(text(name.to_owned()));
+                if *name == self.extension {
+                    tab.into()
+                } else {
+                    tab.style(button::secondary)
+                        .on_press(AppEvent::ShowExtensionLogs(name.to_owned()))
+                        .into()
+                }
+            })
+            .collect::<Vec<_>>())
+        .spacing(5);
+
+        let header = row![
+            button(text("Back")).on_press(AppEvent::ExitToSearch),
+            scrollable(tabs).direction(scrollable::Direction::Horizontal(
+                scrollable::Scrollbar::default()
+            )),
+        ]
+        .spacing(10);
+
+        let entries = self
+            .extensions
+            .iter()
+            .find(|(name, _)| *name == self.extension)
+            .map(|(_, entries)| entries.as_slice())
+            .unwrap_or_default();
+
+        let lines: Element<'_, AppEvent> = if entries.is_empty() {
+            text("No log output yet").into()
+        } else {
+            column(
+                entries
+                    .iter()
+                    .rev()
+                    .map(Self::render_entry)
+                    .collect::<Vec<_>>(),
+            )
+            .spacing(2)
+            .into()
+        };
+
+        container(column![header, scrollable(lines).height(Length::Fill)].spacing(10))
+            .padding(10)
+            .into()
+    }
+
+    fn render_entry(entry: &LogEntry) -> Element<'_, AppEvent> {
+        let line = text(format!(
+            "{} {:<5} {}",
+            humantime::format_rfc3339_seconds(entry.timestamp),
+            entry.level,
+            entry.message
+        ))
+        .size(13);
+
+        match entry.level {
+            log::Level::Error => line.style(text::danger).into(),
+            log::Level::Warn => line.color(Color::from_rgb8(0xd9, 0x8c, 0x1f)).into(),
+            _ => line.into(),
+        }
+    }
+}
@@ -1,7 +1,16 @@
+use detail::DetailView;
+use form::FormView;
+use logs::Logs;
 use search::Search;
 
+pub mod detail;
+pub mod form;
+pub mod logs;
 pub mod search;
 
 pub enum Screen {
-    Search(Search),
+    Search(Box<Search>),
+    Logs(Logs),
+    Detail(Box<DetailView>),
+    Form(Box<FormView>),
 }
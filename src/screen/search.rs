@@ -1,35 +1,472 @@
-use core::{events::AppEvent, state::Item};
+use std::collections::HashMap;
+
+use core::{
+    commands::Action,
+    events::AppEvent,
+    icons::Icon,
+    state::{BadgeColor, Item, Section},
+};
 
 use iced::{
-    Element,
-    widget::{column, container, scrollable, text},
+    Alignment, Color, Element, Length, Task, border,
+    widget::{
+        button, column, container, horizontal_space, image, mouse_area, row, scrollable,
+        scrollable::AbsoluteOffset, stack, svg, text, text_input,
+    },
+};
+use uuid::Uuid;
+
+use crate::{
+    theme::Appearance,
+    widget::{detail::Detail, search_bar::search_bar},
 };
 
-use crate::widget::search_bar::search_bar;
+const FALLBACK_ICON: &[u8] = include_bytes!("../../assets/fallback-icon.svg");
+
+// Fixed sizes of the rows in the result list, so the position of the selected
+// item can be computed to keep it scrolled into view
+const HEADING_HEIGHT: f32 = 18.0;
+const SHOW_MORE_HEIGHT: f32 = 24.0;
+const ITEM_SPACING: f32 = 2.0;
+const SECTION_SPACING: f32 = 10.0;
+// Estimated sizes of the rest of the screen, to fit the window to the results
+const SEARCH_BAR_HEIGHT: f32 = 36.0;
+const LINE_HEIGHT: f32 = 24.0;
+/// Room the preview is given even when fewer results are shown.
+const PREVIEW_MIN_HEIGHT: f32 = 240.0;
+
+/// A message about the last action that was run.
+#[derive(Debug, Clone)]
+pub enum Notice {
+    Info(String),
+    Error(String),
+}
+
+/// The open action panel, mirrored from `AppMode::Actions`.
+#[derive(Debug, Clone)]
+pub struct ActionPanel {
+    pub item_id: Uuid,
+    pub item_title: String,
+    pub filter: String,
+    /// The actions matching the filter, with their index among all of the
+    /// item's actions.
+    pub actions: Vec<(usize, Action)>,
+    pub selected: usize,
+}
 
 #[derive(Default, Clone)]
 pub struct Search {
     pub query: String,
-    pub items: Vec<Item>,
+    /// Whether the query was empty before it last changed, see
+    /// `AppEvent::NavigateBack`.
+    pub was_empty: bool,
+    /// Placeholders of the keyword arguments still to be typed.
+    pub hint: Vec<String>,
+    /// Placeholders and values of the selected item's arguments, while they
+    /// are typed.
+    pub arguments: Vec<(String, String)>,
+    /// Titles of the views down to this one, see `AppState::breadcrumbs`.
+    pub breadcrumbs: Vec<String>,
+    pub sections: Vec<Section>,
+    /// Index of the selected item among the visible items, mirrored from
+    /// `AppState`.
+    pub selected: usize,
+    /// Resolved icons of the items, by the icon the item asked for.
+    pub icons: HashMap<String, Icon>,
+    pub icon_size: u16,
+    pub notice: Option<Notice>,
+    pub actions: Option<Box<ActionPanel>>,
+    /// Width of the preview pane, `None` when previews are disabled.
+    pub preview_width: Option<u16>,
+    /// Preview of the selected item.
+    pub detail: Option<Box<Detail>>,
+    /// Extensions that were disabled after crashing repeatedly, with the
+    /// reason of the last crash.
+    pub faulted: Vec<(String, String)>,
+    /// Scroll position and height of the result list.
+    pub scroll_offset: f32,
+    pub viewport_height: f32,
 }
 
 impl Search {
-    pub fn view(&self) -> Element<'_, AppEvent> {
-        let search_bar = search_bar(self.query.to_owned(), None);
-        let items = scrollable(column(
-            self.items
+    pub fn results_id() -> scrollable::Id {
+        scrollable::Id::new("results")
+    }
+
+    pub fn action_filter_id() -> text_input::Id {
+        text_input::Id::new("action-filter")
+    }
+
+    pub fn view<'a>(&'a self, appearance: &'a Appearance) -> Element<'a, AppEvent> {
+        let search_bar = search_bar(self.query.to_owned(), None, &self.hint, &self.arguments);
+        let breadcrumbs = (self.breadcrumbs.len() > 1).then(|| {
+            text(self.breadcrumbs.join(" › "))
+                .size(12)
+                .style(text::secondary)
+        });
+        let faulted = column(
+            self.faulted
                 .iter()
-                .map(|item| self.render_item(item))
+                .map(|(name, reason)| {
+                    row![
+                        text(format!("Extension \"{name}\" was disabled: {reason}"))
+                            .style(text::danger),
+                        button(text("Logs"))
+                            .style(button::text)
+                            .on_press(AppEvent::ShowExtensionLogs(name.to_owned())),
+                    ]
+                    .spacing(5)
+                    .into()
+                })
                 .collect::<Vec<_>>(),
-        ))
-        .spacing(5);
+        );
+        let notice = self.notice.as_ref().map(|notice| match notice {
+            Notice::Info(message) => text(message.to_owned()),
+            Notice::Error(message) => text(message.to_owned()).style(text::danger),
+        });
+
+        let mut first_index = 0;
+        let sections = self
+            .sections
+            .iter()
+            .map(|section| {
+                let rendered = self.render_section(section, first_index, appearance);
+                first_index += section.visible_items().len();
+                rendered
+            })
+            .collect::<Vec<_>>();
+        let sections = scrollable(column(sections).spacing(SECTION_SPACING))
+            .id(Self::results_id())
+            .on_scroll(|viewport| AppEvent::ResultsScrolled {
+                offset: viewport.absolute_offset().y,
+                height: viewport.bounds().height,
+            })
+            .spacing(5);
+        let results: Element<'_, AppEvent> = match &self.actions {
+            Some(panel) => stack![
+                sections,
+                container(self.render_actions(panel, appearance))
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .align_right(Length::Fill)
+                    .align_bottom(Length::Fill),
+            ]
+            .into(),
+            None => sections.into(),
+        };
+        let results = match (&self.detail, self.preview_width) {
+            (Some(detail), Some(width)) => row![results, detail.view(width, appearance)]
+                .spacing(appearance.spacing)
+                .into(),
+            _ => results,
+        };
+
+        container(
+            column(breadcrumbs.map(Element::from))
+                .push(search_bar)
+                .push(faulted)
+                .push_maybe(notice)
+                .push(results)
+                .spacing(appearance.spacing),
+        )
+        .padding(appearance.spacing)
+        .into()
+    }
+
+    /// Prepares the preview of the selected item, unless it is shown
+    /// already. Previews come with the items, so providers aren't asked
+    /// again. Returns the task reading the file it shows, if any.
+    pub fn refresh_detail(&mut self) -> Task<AppEvent> {
+        if self.preview_width.is_none() {
+            self.detail = None;
+            return Task::none();
+        }
+
+        let item = self
+            .sections
+            .iter()
+            .flat_map(Section::visible_items)
+            .nth(self.selected);
+        let shown = self.detail.as_ref().map(|detail| detail.item_id);
+        if item.map(|item| item.id) == shown {
+            return Task::none();
+        }
+        self.detail = item.and_then(Detail::new).map(Box::new);
+        item.map_or_else(Task::none, Detail::load)
+    }
+
+    /// Keeps the selected item in view, scrolling as little as possible.
+    pub fn scroll_to_selection(&self) -> Task<AppEvent> {
+        let Some((top, bottom)) = self.selected_span() else {
+            return Task::none();
+        };
+        // Nothing to scroll before the list was laid out
+        if self.viewport_height <= 0.0 {
+            return Task::none();
+        }
+
+        let offset = if top < self.scroll_offset {
+            top
+        } else if bottom > self.scroll_offset + self.viewport_height {
+            bottom - self.viewport_height
+        } else {
+            return Task::none();
+        };
+
+        scrollable::scroll_to(Self::results_id(), AbsoluteOffset { x: 0.0, y: offset })
+    }
+
+    /// Number of items that fit in the result list, for page up and down.
+    pub fn page_size(&self) -> usize {
+        (self.viewport_height / (self.row_height() + ITEM_SPACING)).max(1.0) as usize
+    }
 
-        container(column![search_bar, items].spacing(10))
-            .padding(10)
+    /// Height of the screen with `max_rows` rows of results, or only as many
+    /// as there are when `fit` is set. A shown preview keeps its minimum
+    /// height either way.
+    pub fn height(&self, spacing: u16, max_rows: usize, fit: bool) -> f32 {
+        let spacing = f32::from(spacing);
+        let most = max_rows as f32 * (self.row_height() + ITEM_SPACING);
+        let mut results = if fit {
+            self.list_height().min(most)
+        } else {
+            most
+        };
+        if self.detail.is_some() && self.preview_width.is_some() {
+            results = results.max(PREVIEW_MIN_HEIGHT);
+        }
+
+        // The search bar, faulted extensions and results are always there
+        let mut parts = 3.0;
+        let mut height = SEARCH_BAR_HEIGHT + self.faulted.len() as f32 * LINE_HEIGHT + results;
+        if self.breadcrumbs.len() > 1 {
+            parts += 1.0;
+            height += LINE_HEIGHT;
+        }
+        if self.notice.is_some() {
+            parts += 1.0;
+            height += LINE_HEIGHT;
+        }
+
+        height + (parts - 1.0) * spacing + 2.0 * spacing
+    }
+
+    fn row_height(&self) -> f32 {
+        // Room for a title and a description next to the icon
+        f32::from(self.icon_size).max(32.0) + 8.0
+    }
+
+    /// Top and bottom of the selected item in the result list.
+    fn selected_span(&self) -> Option<(f32, f32)> {
+        let mut y = 0.0;
+        let mut index = 0;
+        for (position, section) in self.sections.iter().enumerate() {
+            if position > 0 {
+                y += SECTION_SPACING;
+            }
+            if section.title.is_some() {
+                y += HEADING_HEIGHT + ITEM_SPACING;
+            }
+            for _ in section.visible_items() {
+                if index == self.selected {
+                    return Some((y, y + self.row_height()));
+                }
+                y += self.row_height() + ITEM_SPACING;
+                index += 1;
+            }
+            if section.hidden_count() > 0 {
+                y += SHOW_MORE_HEIGHT;
+            } else {
+                y -= ITEM_SPACING;
+            }
+        }
+
+        None
+    }
+
+    /// Height of the whole result list, including what is scrolled away.
+    fn list_height(&self) -> f32 {
+        let mut y = 0.0;
+        for (position, section) in self.sections.iter().enumerate() {
+            if position > 0 {
+                y += SECTION_SPACING;
+            }
+            if section.title.is_some() {
+                y += HEADING_HEIGHT + ITEM_SPACING;
+            }
+            let items = section.visible_items().len() as f32;
+            y += items * (self.row_height() + ITEM_SPACING);
+            if section.hidden_count() > 0 {
+                y += SHOW_MORE_HEIGHT;
+            } else {
+                y -= ITEM_SPACING;
+            }
+        }
+
+        y.max(0.0)
+    }
+
+    fn render_section<'a>(
+        &'a self,
+        section: &'a Section,
+        first_index: usize,
+        appearance: &'a Appearance,
+    ) -> Element<'a, AppEvent> {
+        let heading = section.title.as_ref().map(|title| {
+            container(text(title.to_owned()).size(12).style(text::secondary)).height(HEADING_HEIGHT)
+        });
+        let items = section
+            .visible_items()
+            .iter()
+            .enumerate()
+            .map(|(offset, item)| self.render_item(item, first_index + offset, appearance));
+
+        let hidden = section.hidden_count();
+        let show_more = (hidden > 0).then(|| {
+            button(text(format!("Show {hidden} more")).size(12))
+                .style(button::text)
+                .height(SHOW_MORE_HEIGHT)
+                .on_press(AppEvent::ExpandSection(section.id.to_owned()))
+        });
+
+        column(heading.map(Element::from))
+            .extend(items)
+            .push_maybe(show_more)
+            .spacing(ITEM_SPACING)
+            .into()
+    }
+
+    fn render_item<'a>(
+        &'a self,
+        item: &'a Item,
+        index: usize,
+        appearance: &'a Appearance,
+    ) -> Element<'a, AppEvent> {
+        let description = item
+            .description
+            .as_ref()
+            .map(|description| text(description.to_owned()).size(12).style(text::secondary));
+        let badges = item.badges.iter().map(|badge| {
+            // Hex colors that don't parse are shown gray
+            let [r, g, b] = badge
+                .color
+                .rgb()
+                .or(BadgeColor::Gray.rgb())
+                .unwrap_or_default();
+            let color = Color::from_rgb8(r, g, b);
+            container(text(badge.text.to_owned()).size(11).color(Color::WHITE))
+                .padding([1, 6])
+                .style(move |_| container::Style {
+                    background: Some(color.into()),
+                    border: border::rounded(8),
+                    ..Default::default()
+                })
+                .into()
+        });
+        let accessories = item.accessories.iter().map(|accessory| {
+            text(accessory.text.to_owned())
+                .size(12)
+                .style(text::secondary)
+                .into()
+        });
+
+        let content = row![
+            self.render_icon(item.icon.as_deref(), f32::from(self.icon_size)),
+            column![text(item.title.to_owned())].push_maybe(description)
+        ]
+        .push(horizontal_space())
+        .extend(badges)
+        .extend(accessories)
+        .spacing(5)
+        .align_y(Alignment::Center);
+
+        let selected = index == self.selected;
+        let row = container(content)
+            .height(self.row_height())
+            .padding([4, 8])
+            .align_y(Alignment::Center)
+            .style(move |_| appearance.row(selected));
+
+        mouse_area(row)
+            .on_press(AppEvent::QuickSelect(index))
             .into()
     }
 
-    fn render_item(&self, item: &Item) -> Element<'_, AppEvent> {
-        text(item.title.to_owned()).into()
+    fn render_actions<'a>(
+        &'a self,
+        panel: &'a ActionPanel,
+        appearance: &'a Appearance,
+    ) -> Element<'a, AppEvent> {
+        let filter = text_input("Filter actions...", &panel.filter)
+            .id(Self::action_filter_id())
+            .on_input(AppEvent::ActionFilterChanged);
+
+        let actions = panel
+            .actions
+            .iter()
+            .enumerate()
+            .map(|(position, (index, action))| {
+                let description = action.description.as_ref().map(|description| {
+                    text(description.to_owned()).size(12).style(text::secondary)
+                });
+                let shortcut = action
+                    .shortcut
+                    .as_ref()
+                    .map(|shortcut| text(shortcut.to_owned()).size(12).style(text::secondary));
+                let icon = action
+                    .icon
+                    .as_deref()
+                    .map(|icon| self.render_icon(Some(icon), 16.0));
+                let content = row(icon)
+                    .push(column![text(action.title.to_owned())].push_maybe(description))
+                    .push(horizontal_space())
+                    .push_maybe(shortcut)
+                    .spacing(5)
+                    .align_y(Alignment::Center);
+
+                let selected = position == panel.selected;
+                let row = container(content)
+                    .padding([4, 8])
+                    .width(Length::Fill)
+                    .style(move |_| appearance.row(selected));
+                mouse_area(row)
+                    .on_press(AppEvent::ItemActivated(panel.item_id, *index))
+                    .into()
+            })
+            .collect::<Vec<_>>();
+        let actions: Element<'_, AppEvent> = if actions.is_empty() {
+            text("No matching actions").style(text::secondary).into()
+        } else {
+            scrollable(column(actions).spacing(ITEM_SPACING)).into()
+        };
+
+        container(
+            column![
+                text(panel.item_title.to_owned())
+                    .size(12)
+                    .style(text::secondary),
+                filter,
+                actions,
+            ]
+            .spacing(5),
+        )
+        .width(320)
+        .max_height(300)
+        .padding(8)
+        .style(|_| appearance.panel())
+        .into()
+    }
+
+    /// The icon resolved for `spec`. Items without an icon, or with one that
+    /// couldn't be resolved, get the fallback icon.
+    fn render_icon(&self, spec: Option<&str>, size: f32) -> Element<'_, AppEvent> {
+        match spec.and_then(|spec| self.icons.get(spec)) {
+            Some(Icon::Raster(path)) => image(path).width(size).height(size).into(),
+            Some(Icon::Svg(path)) => svg(path).width(size).height(size).into(),
+            None => svg(svg::Handle::from_memory(FALLBACK_ICON))
+                .width(size)
+                .height(size)
+                .into(),
+        }
     }
 }
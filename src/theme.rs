@@ -0,0 +1,82 @@
+use core::theme::{Rgb, ThemeDefinition};
+
+use iced::{Color, Font, Theme, border, theme::Palette, widget::container};
+
+/// The loaded theme, in the form the widgets take.
+#[derive(Debug, Clone)]
+pub struct Appearance {
+    pub theme: Theme,
+    pub surface: Color,
+    pub border: Color,
+    pub selected_background: Color,
+    pub selected_text: Color,
+    pub selected_border: Option<Color>,
+    pub radius: f32,
+    pub spacing: u16,
+}
+
+impl Appearance {
+    pub fn new(definition: &ThemeDefinition) -> Self {
+        let colors = &definition.colors;
+        let palette = Palette {
+            background: color(colors.background),
+            text: color(colors.text),
+            primary: color(colors.accent),
+            success: color(colors.success),
+            danger: color(colors.danger),
+        };
+
+        Self {
+            theme: Theme::custom(definition.name.clone(), palette),
+            surface: color(colors.surface),
+            border: color(colors.border),
+            selected_background: color(definition.selected_row.background),
+            selected_text: color(definition.selected_row.text),
+            selected_border: definition.selected_row.border.map(color),
+            radius: definition.corner_radius,
+            spacing: definition.spacing,
+        }
+    }
+
+    /// A row in a list, highlighted when selected.
+    pub fn row(&self, selected: bool) -> container::Style {
+        if !selected {
+            return container::Style::default();
+        }
+
+        let border = border::rounded(self.radius);
+        container::Style {
+            background: Some(self.selected_background.into()),
+            text_color: Some(self.selected_text),
+            border: match self.selected_border {
+                Some(color) => border.color(color).width(1),
+                None => border,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// A panel drawn over the results, e.g. the action panel.
+    pub fn panel(&self) -> container::Style {
+        container::Style {
+            background: Some(self.surface.into()),
+            border: border::rounded(self.radius + 2.0)
+                .color(self.border)
+                .width(1),
+            ..Default::default()
+        }
+    }
+}
+
+/// The font family of `definition`, as the default font of the application.
+/// Fonts can only be set at startup, so the name is leaked once.
+pub fn font(definition: &ThemeDefinition) -> Font {
+    match &definition.font.family {
+        Some(family) => Font::with_name(Box::leak(family.clone().into_boxed_str())),
+        None => Font::default(),
+    }
+}
+
+fn color(Rgb([r, g, b]): Rgb) -> Color {
+    Color::from_rgb8(r, g, b)
+}
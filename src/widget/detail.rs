@@ -0,0 +1,182 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use core::{
+    events::AppEvent,
+    state::{Item, Metadata, Preview},
+};
+
+use iced::{
+    Element, Length, Task,
+    widget::{column, container, horizontal_rule, image, markdown, row, scrollable, text},
+};
+use uuid::Uuid;
+
+use crate::theme::Appearance;
+
+/// Bytes of a text file shown in its preview.
+const FILE_EXCERPT_LEN: u64 = 8 * 1024;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp", "ico"];
+
+/// The preview of an item, prepared once when the item gets selected so
+/// Markdown isn't parsed and files aren't read on every redraw. Files are
+/// read in the background, see `load`.
+#[derive(Debug, Clone)]
+pub struct Detail {
+    pub item_id: Uuid,
+    body: Body,
+    metadata: Vec<Metadata>,
+}
+
+#[derive(Debug, Clone)]
+enum Body {
+    Text(String),
+    Markdown(Vec<markdown::Item>),
+    Image(PathBuf),
+    /// A file that is still being read.
+    Loading,
+    /// A preview that couldn't be loaded, with the reason.
+    Unavailable(String),
+}
+
+impl Detail {
+    /// The preview of `item`, if it has anything to show.
+    pub fn new(item: &Item) -> Option<Self> {
+        let body = item.preview.as_ref().map(body);
+        if body.is_none() && item.metadata.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            item_id: item.id,
+            body: body.unwrap_or(Body::Text(String::new())),
+            metadata: item.metadata.clone(),
+        })
+    }
+
+    /// Reads the file shown by the preview of `item` off the UI thread,
+    /// for `loaded`. Other previews need nothing read.
+    pub fn load(item: &Item) -> Task<AppEvent> {
+        let Some(preview @ (Preview::Image { .. } | Preview::File { .. })) = item.preview.clone()
+        else {
+            return Task::none();
+        };
+
+        let item_id = item.id;
+        Task::perform(
+            tokio::task::spawn_blocking(move || read(preview)),
+            move |preview| AppEvent::PreviewLoaded {
+                item_id,
+                preview: preview.unwrap_or_else(|e| Err(e.to_string())),
+            },
+        )
+    }
+
+    /// Shows the file read by `load`, unless another item was selected
+    /// meanwhile.
+    pub fn loaded(&mut self, item_id: Uuid, preview: Result<Preview, String>) {
+        if item_id == self.item_id && matches!(self.body, Body::Loading) {
+            self.body = match preview {
+                Ok(Preview::Image { source }) => Body::Image(PathBuf::from(source)),
+                Ok(preview) => body(&preview),
+                Err(reason) => Body::Unavailable(reason),
+            };
+        }
+    }
+
+    pub fn view<'a>(&'a self, width: u16, appearance: &'a Appearance) -> Element<'a, AppEvent> {
+        let body: Element<'_, AppEvent> = match &self.body {
+            Body::Text(content) => text(content.to_owned()).size(13).into(),
+            Body::Markdown(items) => markdown::view(
+                items,
+                markdown::Settings::with_text_size(13),
+                markdown::Style::from_palette(appearance.theme.palette()),
+            )
+            .map(|url| AppEvent::OpenLink(url.to_string())),
+            Body::Image(path) => image(path).width(Length::Fill).into(),
+            Body::Loading => text("Loading…").size(13).style(text::secondary).into(),
+            Body::Unavailable(reason) => text(reason.to_owned()).style(text::secondary).into(),
+        };
+
+        let metadata = self.metadata.iter().map(|entry| {
+            row![
+                text(entry.label.to_owned())
+                    .size(12)
+                    .style(text::secondary)
+                    .width(Length::FillPortion(1)),
+                text(entry.value.to_owned())
+                    .size(12)
+                    .width(Length::FillPortion(2)),
+            ]
+            .spacing(5)
+            .into()
+        });
+        let metadata = (!self.metadata.is_empty())
+            .then(|| column![horizontal_rule(1)].extend(metadata).spacing(4));
+
+        container(scrollable(
+            column![body]
+                .push_maybe(metadata)
+                .spacing(10)
+                .padding([0, 8]),
+        ))
+        .width(width)
+        .height(Length::Fill)
+        .into()
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// What `preview` shows, without reading any files.
+fn body(preview: &Preview) -> Body {
+    match preview {
+        Preview::Text { text } => Body::Text(text.to_owned()),
+        Preview::Markdown { markdown } => Body::Markdown(markdown::parse(markdown).collect()),
+        Preview::Image { .. } | Preview::File { .. } => Body::Loading,
+    }
+}
+
+/// What the file of `preview` shows.
+fn read(preview: Preview) -> Result<Preview, String> {
+    match preview {
+        Preview::Image { source } => find_image(Path::new(&source)),
+        Preview::File { path } => read_file(&path),
+        preview => Ok(preview),
+    }
+}
+
+fn find_image(path: &Path) -> Result<Preview, String> {
+    if path.is_file() {
+        Ok(Preview::Image {
+            source: path.display().to_string(),
+        })
+    } else {
+        Err(format!("Image not found: {}", path.display()))
+    }
+}
+
+/// Images are shown as such, other files by the start of their content.
+fn read_file(path: &Path) -> Result<Preview, String> {
+    if is_image(path) {
+        return find_image(path);
+    }
+
+    let mut excerpt = Vec::new();
+    match File::open(path).and_then(|file| file.take(FILE_EXCERPT_LEN).read_to_end(&mut excerpt)) {
+        // Binary files have nothing readable to show
+        Ok(_) if excerpt.contains(&0) => Err(path.display().to_string()),
+        Ok(_) => Ok(Preview::Text {
+            text: String::from_utf8_lossy(&excerpt).into_owned(),
+        }),
+        Err(e) => Err(format!("Failed to read {}: {e}", path.display())),
+    }
+}
@@ -1 +1,2 @@
+pub mod detail;
 pub mod search_bar;
@@ -1,9 +1,48 @@
 use core::events::AppEvent;
 
-use iced::{Element, widget::text_input};
+use iced::{
+    Alignment, Element, Length,
+    widget::{row, text, text_input},
+};
 
-pub fn search_bar(query: String, place_holder: Option<&str>) -> Element<'_, AppEvent> {
-    text_input(place_holder.unwrap_or("Type to search..."), &query)
-        .on_input(AppEvent::QueryChanged)
+pub fn id() -> text_input::Id {
+    text_input::Id::new("search-bar")
+}
+
+/// Id of the input of the argument at `index`.
+pub fn argument_id(index: usize) -> text_input::Id {
+    text_input::Id::new(format!("argument-{index}"))
+}
+
+/// The query input, followed by an input for each of the selected item's
+/// `arguments` (placeholder and value) while they are typed. Otherwise
+/// `hint` holds the placeholders of the keyword arguments still to be typed.
+pub fn search_bar<'a>(
+    query: String,
+    place_holder: Option<&'a str>,
+    hint: &'a [String],
+    arguments: &'a [(String, String)],
+) -> Element<'a, AppEvent> {
+    let input = text_input(place_holder.unwrap_or("Type to search..."), &query)
+        .id(id())
+        .on_input(AppEvent::QueryChanged);
+    let hint = (arguments.is_empty() && !hint.is_empty())
+        .then(|| text(hint.join(" ")).style(text::secondary));
+    let arguments = arguments
+        .iter()
+        .enumerate()
+        .map(|(index, (placeholder, value))| {
+            text_input(placeholder, value)
+                .id(argument_id(index))
+                .on_input(move |value| AppEvent::ArgumentChanged(index, value))
+                .width(140)
+                .into()
+        });
+
+    row![input.width(Length::Fill)]
+        .push_maybe(hint)
+        .extend(arguments)
+        .spacing(5)
+        .align_y(Alignment::Center)
         .into()
 }